@@ -1,23 +1,225 @@
-use std::boxed::Box;
+use std::{boxed::Box, env};
 
+use log::*;
+use rocket::{
+    http::{ContentType, Header, Status},
+    request::Request,
+    response::{self, Responder, Response},
+};
+use rocket_contrib::json::Json;
 use thiserror::Error;
 
+lazy_static! {
+    /// Whether errors are returned in the pre-problem+json format(timestamp, scope and message)
+    static ref LEGACY_ERRORS: bool = {
+        match env::var("ERROR_FORMAT")
+            .unwrap_or_else(|_| String::from("PROBLEM"))
+            .as_str()
+        {
+            "LEGACY" => true,
+            _ => false,
+        }
+    };
+    /// Whether the causes of errors are included in responses, set through ERROR_DEBUG=TRUE
+    static ref DEBUG_ERRORS: bool = {
+        match env::var("ERROR_DEBUG")
+            .unwrap_or_else(|_| String::from("FALSE"))
+            .as_str()
+        {
+            "TRUE" => true,
+            _ => false,
+        }
+    };
+}
+
+/// Trait implemented by the errors that can be wrapped by the feeder Error
+pub trait FeederError: std::error::Error {
+    /// Get the http status that corresponds to this error
+    fn status(&self) -> Status;
+
+    /// Get the stable, machine-readable code of this error
+    fn code(&self) -> &'static str;
+
+    /// Get the request field that caused this error, if any
+    fn field(&self) -> Option<&'static str> {
+        Option::None
+    }
+}
+
 /// Error struct used by feeder
+/// Serializes to the legacy error body, responses use problem details(RFC 7807) by default
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Error {
     timestamp: u64,
     scope: String,
     message: String,
+    #[serde(skip)]
+    status: u16,
+    #[serde(skip)]
+    code: String,
+    #[serde(skip)]
+    field: Option<String>,
+    #[serde(skip)]
+    backend: Option<BackendErrorKind>,
+    /// The messages of the chain of errors that caused this error, outermost first
+    #[serde(skip)]
+    causes: Vec<String>,
+}
+
+/// The kinds of failures reported by a database backend
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendErrorKind {
+    /// A unique index was violated
+    DuplicateKey,
+    /// The database did not answer in time
+    Timeout,
+    /// The database could not be reached
+    ConnectionRefused,
+    Other,
+}
+
+impl BackendErrorKind {
+    /// Get the http status of this kind of failure, if it is more specific than the one of the
+    /// error it caused
+    fn status(self) -> Option<Status> {
+        match self {
+            BackendErrorKind::DuplicateKey => Option::Some(Status::Conflict),
+            BackendErrorKind::Timeout => Option::Some(Status::ServiceUnavailable),
+            BackendErrorKind::ConnectionRefused => Option::Some(Status::ServiceUnavailable),
+            BackendErrorKind::Other => Option::None,
+        }
+    }
+}
+
+/// Problem details(RFC 7807) body of an error response
+#[derive(Clone, Debug, Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    _type: &'static str,
+    title: &'static str,
+    status: u16,
+    code: &'a str,
+    detail: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    instance: &'a str,
+    timestamp: u64,
+    scope: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backend: Option<BackendErrorKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    causes: Option<&'a Vec<String>>,
 }
 
 impl Error {
     /// Create a new Error
-    pub fn new(scope: String, error: Box<dyn std::error::Error>) -> Self {
+    pub fn new(scope: String, error: Box<dyn FeederError>) -> Self {
         Error {
             timestamp: super::timestamp(),
             scope,
             message: format!("{}", error),
+            status: error.status().code,
+            code: error.code().to_string(),
+            field: error.field().map(|value| value.to_string()),
+            backend: Option::None,
+            causes: Vec::new(),
+        }
+    }
+
+    /// Attach the backend error that caused this error
+    /// The whole chain of the source is kept, and the kind of failure overrides the http status
+    /// when it is more specific(e.g. 503 when the database is down)
+    pub fn caused_by(
+        mut self,
+        kind: BackendErrorKind,
+        source: &(dyn std::error::Error + 'static),
+    ) -> Self {
+        let mut cause: Option<&(dyn std::error::Error + 'static)> = Option::Some(source);
+        while let Some(value) = cause {
+            self.causes.push(format!("{}", value));
+            cause = value.source();
+        }
+        if let Some(status) = kind.status() {
+            self.status = status.code;
         }
+        self.backend = Option::Some(kind);
+
+        debug!("{}({:?})", self, kind);
+        self
+    }
+
+    /// Get the http status of this error
+    pub fn status(&self) -> Status {
+        Status::from_code(self.status).unwrap_or(Status::InternalServerError)
+    }
+
+    /// Get the machine-readable code of this error
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+/// Get the content type of problem details
+pub fn problem_content_type() -> ContentType {
+    ContentType::new("application", "problem+json")
+}
+
+/// Respond with the error as problem details(or the legacy json body), using its http status
+impl<'r> Responder<'r> for Error {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let status: Status = self.status();
+        // Bearer tokens are the only way to authenticate, as required along with 401 by RFC 7235
+        let challenge: Option<Header> = if status == Status::Unauthorized {
+            Option::Some(Header::new("WWW-Authenticate", "Bearer"))
+        } else {
+            Option::None
+        };
+        if *LEGACY_ERRORS {
+            let mut response: Response<'r> = Response::build_from(Json(self).respond_to(request)?)
+                .status(status)
+                .finalize();
+            if let Some(value) = challenge {
+                response.set_header(value);
+            }
+            return Result::Ok(response);
+        }
+
+        let problem: Problem = Problem {
+            _type: "about:blank",
+            title: status.reason,
+            status: status.code,
+            code: &self.code,
+            detail: &self.message,
+            field: self.field.as_ref().map(|value| value.as_str()),
+            instance: request.uri().path(),
+            timestamp: self.timestamp,
+            scope: &self.scope,
+            backend: self.backend,
+            causes: if *DEBUG_ERRORS && !self.causes.is_empty() {
+                Option::Some(&self.causes)
+            } else {
+                Option::None
+            },
+        };
+        let mut response: Response<'r> = Response::build_from(Json(problem).respond_to(request)?)
+            .status(status)
+            .header(problem_content_type())
+            .finalize();
+        if let Some(value) = challenge {
+            response.set_header(value);
+        }
+        Result::Ok(response)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.scope, self.message)?;
+        for cause in &self.causes {
+            write!(f, ": {}", cause)?;
+        }
+        Result::Ok(())
     }
 }
 
@@ -30,6 +232,26 @@ pub enum FeedRouterError {
     ModelAndArgUuidsDiffer,
 }
 
+impl FeederError for FeedRouterError {
+    fn status(&self) -> Status {
+        match self {
+            FeedRouterError::NoUuid => Status::BadRequest,
+            FeedRouterError::ModelAndArgUuidsDiffer => Status::Conflict,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            FeedRouterError::NoUuid => "uuid_missing",
+            FeedRouterError::ModelAndArgUuidsDiffer => "uuid_mismatch",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        Option::Some("uuid")
+    }
+}
+
 /// Errors that can be produced by the feed items router
 #[derive(Clone, Debug, Deserialize, Error, Serialize)]
 pub enum FeedItemsRouterError {
@@ -47,6 +269,26 @@ pub enum FeedItemsRouterError {
     FailedToGetFeedWithItems,
 }
 
+impl FeederError for FeedItemsRouterError {
+    fn status(&self) -> Status {
+        match self {
+            FeedItemsRouterError::FeedHasNoItems => Status::NotFound,
+            FeedItemsRouterError::NoFeedItemInFeed => Status::NotFound,
+            _ => Status::InternalServerError,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            FeedItemsRouterError::FeedHasNoItems => "feed_has_no_items",
+            FeedItemsRouterError::NoFeedItemInFeed => "item_not_found",
+            FeedItemsRouterError::FailedToRetrieveItems => "items_retrieval_failed",
+            FeedItemsRouterError::CouldNotCreateFeedItem => "item_creation_failed",
+            FeedItemsRouterError::FailedToGetFeedWithItems => "feed_retrieval_failed",
+        }
+    }
+}
+
 /// Errors that can be produced by uuid checking
 #[derive(Clone, Debug, Deserialize, Error, Serialize)]
 pub enum UuidError {
@@ -54,6 +296,20 @@ pub enum UuidError {
     UuidNotValid { err: String },
 }
 
+impl FeederError for UuidError {
+    fn status(&self) -> Status {
+        Status::BadRequest
+    }
+
+    fn code(&self) -> &'static str {
+        "uuid_invalid"
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        Option::Some("uuid")
+    }
+}
+
 /// Errors that can be produced by db models
 #[derive(Clone, Debug, Deserialize, Error, Serialize)]
 pub enum ModelError {
@@ -67,6 +323,40 @@ pub enum ModelError {
     ModelHasNoDescription,
     #[error("model has no link")]
     ModelHasNoLink,
+    #[error("items cannot be created {state}, only draft or published")]
+    InvalidInitialState { state: String },
+}
+
+impl FeederError for ModelError {
+    fn status(&self) -> Status {
+        match self {
+            // the checksum is computed by feeder, not sent by the client
+            ModelError::FailedToComputeChecksum => Status::InternalServerError,
+            _ => Status::UnprocessableEntity,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ModelError::FailedToComputeChecksum => "checksum_failed",
+            ModelError::NoItems => "model_has_no_items",
+            ModelError::ModelHasNoTitle => "title_missing",
+            ModelError::ModelHasNoDescription => "description_missing",
+            ModelError::ModelHasNoLink => "link_missing",
+            ModelError::InvalidInitialState { .. } => "state_invalid",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            ModelError::FailedToComputeChecksum => Option::None,
+            ModelError::NoItems => Option::Some("items"),
+            ModelError::ModelHasNoTitle => Option::Some("title"),
+            ModelError::ModelHasNoDescription => Option::Some("description"),
+            ModelError::ModelHasNoLink => Option::Some("link"),
+            ModelError::InvalidInitialState { .. } => Option::Some("state"),
+        }
+    }
 }
 
 /// Errors that can be produced by the feed db
@@ -84,6 +374,40 @@ pub enum FeedDbError {
     FailedToDeleteFeed,
     #[error("failed to update feed")]
     FailedToUpdateFeed,
+    #[error("failed to migrate the database")]
+    FailedToMigrate,
+    #[error("the checksum of the feed does not match the expected one")]
+    ChecksumMismatch,
+}
+
+impl FeederError for FeedDbError {
+    fn status(&self) -> Status {
+        match self {
+            FeedDbError::NoFeedFound => Status::NotFound,
+            FeedDbError::ChecksumMismatch => Status::PreconditionFailed,
+            _ => Status::InternalServerError,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            FeedDbError::FailedToSaveFeed => "feed_save_failed",
+            FeedDbError::FailedToGetFeeds => "feed_retrieval_failed",
+            FeedDbError::NoFeedFound => "feed_not_found",
+            FeedDbError::FeedHasNoChecksum => "feed_has_no_checksum",
+            FeedDbError::FailedToDeleteFeed => "feed_delete_failed",
+            FeedDbError::FailedToUpdateFeed => "feed_update_failed",
+            FeedDbError::FailedToMigrate => "migration_failed",
+            FeedDbError::ChecksumMismatch => "checksum_mismatch",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            FeedDbError::ChecksumMismatch => Option::Some("checksum"),
+            _ => Option::None,
+        }
+    }
 }
 
 /// Errors that can be produced by the feed item db
@@ -101,6 +425,246 @@ pub enum FeedItemDbError {
     NoItemFound,
     #[error("no checksum")]
     NoChecksum,
+    #[error("the checksum of the feed item does not match the expected one")]
+    ChecksumMismatch,
+}
+
+impl FeederError for FeedItemDbError {
+    fn status(&self) -> Status {
+        match self {
+            FeedItemDbError::NoItemFound => Status::NotFound,
+            FeedItemDbError::ChecksumMismatch => Status::PreconditionFailed,
+            _ => Status::InternalServerError,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            FeedItemDbError::FailedToDelete => "item_delete_failed",
+            FeedItemDbError::FailedToUpdate => "item_update_failed",
+            FeedItemDbError::FailedToSaveItem => "item_save_failed",
+            FeedItemDbError::FailedToGetItems => "items_retrieval_failed",
+            FeedItemDbError::NoItemFound => "item_not_found",
+            FeedItemDbError::NoChecksum => "item_has_no_checksum",
+            FeedItemDbError::ChecksumMismatch => "checksum_mismatch",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            FeedItemDbError::ChecksumMismatch => Option::Some("checksum"),
+            _ => Option::None,
+        }
+    }
+}
+
+/// Errors that can be produced by the storage backend, regardless of which one is used
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum StorageError {
+    #[error("failed to get a connection to the storage backend")]
+    NoConnection,
+}
+
+impl FeederError for StorageError {
+    fn status(&self) -> Status {
+        Status::ServiceUnavailable
+    }
+
+    fn code(&self) -> &'static str {
+        "storage_unavailable"
+    }
+}
+
+/// Errors that can be produced by listing queries
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum QueryError {
+    #[error("the limit must be between 1 and {max}")]
+    InvalidLimit { max: u32 },
+    #[error("invalid cursor")]
+    InvalidCursor,
+    #[error("invalid sort: {sort}")]
+    InvalidSort { sort: String },
+    #[error("invalid order: {order}")]
+    InvalidOrder { order: String },
+    #[error("invalid state: {state}")]
+    InvalidState { state: String },
+}
+
+impl FeederError for QueryError {
+    fn status(&self) -> Status {
+        Status::BadRequest
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            QueryError::InvalidLimit { .. } => "limit_invalid",
+            QueryError::InvalidCursor => "cursor_invalid",
+            QueryError::InvalidSort { .. } => "sort_invalid",
+            QueryError::InvalidOrder { .. } => "order_invalid",
+            QueryError::InvalidState { .. } => "state_invalid",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            QueryError::InvalidLimit { .. } => Option::Some("limit"),
+            QueryError::InvalidCursor => Option::Some("cursor"),
+            QueryError::InvalidSort { .. } => Option::Some("sort"),
+            QueryError::InvalidOrder { .. } => Option::Some("order"),
+            QueryError::InvalidState { .. } => Option::Some("state"),
+        }
+    }
+}
+
+/// Errors that can be produced by moving a feed item through its lifecycle
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum ItemStateError {
+    #[error("a {from} item cannot be {to}")]
+    InvalidTransition { from: String, to: String },
+    #[error("the state of the item changed in the meantime")]
+    StateChanged,
+}
+
+impl FeederError for ItemStateError {
+    fn status(&self) -> Status {
+        Status::Conflict
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ItemStateError::InvalidTransition { .. } => "transition_invalid",
+            ItemStateError::StateChanged => "state_changed",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        Option::Some("state")
+    }
+}
+
+/// Errors that can be produced by conditional requests
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum PreconditionError {
+    #[error("the current checksum must be sent in the If-Match header or the checksum field")]
+    ChecksumRequired,
+    #[error("weak entity tags never match in the If-Match header")]
+    WeakEntityTag,
+}
+
+impl FeederError for PreconditionError {
+    fn status(&self) -> Status {
+        match self {
+            PreconditionError::ChecksumRequired => Status::PreconditionRequired,
+            PreconditionError::WeakEntityTag => Status::PreconditionFailed,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            PreconditionError::ChecksumRequired => "checksum_required",
+            PreconditionError::WeakEntityTag => "etag_weak",
+        }
+    }
+
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            PreconditionError::ChecksumRequired => Option::Some("checksum"),
+            PreconditionError::WeakEntityTag => Option::None,
+        }
+    }
+}
+
+/// Errors that can be produced by authorizing a request
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum AuthError {
+    #[error("this request requires a bearer token in the Authorization header")]
+    TokenRequired,
+    #[error("the bearer token is not valid")]
+    InvalidToken,
+}
+
+impl FeederError for AuthError {
+    fn status(&self) -> Status {
+        match self {
+            AuthError::TokenRequired => Status::Unauthorized,
+            AuthError::InvalidToken => Status::Forbidden,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            AuthError::TokenRequired => "token_required",
+            AuthError::InvalidToken => "invalid_token",
+        }
+    }
+}
+
+/// Errors that can be produced by applying patch documents
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum PatchError {
+    #[error("failed to serialize the model before patching it")]
+    FailedToSerialize,
+    #[error("the patch contains an invalid pointer")]
+    InvalidPointer,
+    #[error("a test operation of the patch failed")]
+    TestFailed,
+    #[error("the patched model is not valid: {err}")]
+    InvalidModel { err: String },
+}
+
+impl FeederError for PatchError {
+    fn status(&self) -> Status {
+        match self {
+            PatchError::FailedToSerialize => Status::InternalServerError,
+            // the patch does not apply to the current state of the model
+            PatchError::TestFailed => Status::Conflict,
+            _ => Status::UnprocessableEntity,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            PatchError::FailedToSerialize => "patch_serialization_failed",
+            PatchError::InvalidPointer => "patch_pointer_invalid",
+            PatchError::TestFailed => "patch_test_failed",
+            PatchError::InvalidModel { .. } => "patch_result_invalid",
+        }
+    }
+}
+
+/// Errors that can be produced while reading syndication documents
+#[derive(Clone, Debug, Deserialize, Error, Serialize)]
+pub enum SyndicationError {
+    #[error("invalid document: {err}")]
+    InvalidDocument { err: String },
+    #[error("the document is neither a rss nor an atom feed")]
+    UnsupportedDocument,
+    #[error("the document has no channel")]
+    NoChannel,
+    #[error("the document has no body")]
+    NoBody,
+    #[error("the document is larger than {limit} bytes")]
+    DocumentTooLarge { limit: u64 },
+}
+
+impl FeederError for SyndicationError {
+    fn status(&self) -> Status {
+        match self {
+            SyndicationError::InvalidDocument { .. } => Status::BadRequest,
+            SyndicationError::DocumentTooLarge { .. } => Status::PayloadTooLarge,
+            _ => Status::UnprocessableEntity,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            SyndicationError::InvalidDocument { .. } => "document_invalid",
+            SyndicationError::UnsupportedDocument => "document_unsupported",
+            SyndicationError::NoChannel => "document_has_no_channel",
+            SyndicationError::NoBody => "document_has_no_body",
+            SyndicationError::DocumentTooLarge { .. } => "document_too_large",
+        }
+    }
 }
 
 /// Errors that can be produced by Rocket catchers
@@ -125,3 +689,33 @@ pub enum HttpError {
     #[error("service unavailable")]
     ServiceUnavailable,
 }
+
+impl FeederError for HttpError {
+    fn status(&self) -> Status {
+        match self {
+            HttpError::BadRequest => Status::BadRequest,
+            HttpError::Unauthorized => Status::Unauthorized,
+            HttpError::Forbidden => Status::Forbidden,
+            HttpError::NotFound => Status::NotFound,
+            HttpError::NotAcceptable => Status::NotAcceptable,
+            HttpError::UnprocessableEntity => Status::UnprocessableEntity,
+            HttpError::Ise => Status::InternalServerError,
+            HttpError::NotImplemented => Status::NotImplemented,
+            HttpError::ServiceUnavailable => Status::ServiceUnavailable,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            HttpError::BadRequest => "bad_request",
+            HttpError::Unauthorized => "unauthorized",
+            HttpError::Forbidden => "forbidden",
+            HttpError::NotFound => "not_found",
+            HttpError::NotAcceptable => "not_acceptable",
+            HttpError::UnprocessableEntity => "unprocessable_entity",
+            HttpError::Ise => "internal_server_error",
+            HttpError::NotImplemented => "not_implemented",
+            HttpError::ServiceUnavailable => "service_unavailable",
+        }
+    }
+}
@@ -1,23 +1,28 @@
 pub mod errors;
 pub mod report;
+pub mod time;
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use rocket::response::Content;
 use rocket_contrib::json::Json;
 
 /// Type that makes it easier to represent a Json result
-pub type JsonResult<T> = Result<Json<T>, Json<errors::Error>>;
+pub type JsonResult<T> = Result<Json<T>, errors::Error>;
+
+/// Type that makes it easier to represent a rendered document(rss, atom etc.) result
+pub type DocumentResult = Result<Content<String>, errors::Error>;
 
 /// Type that makes it easier to represent a result returned by feeder functions
 pub type DbResult<T> = Result<T, errors::Error>;
 
-/// Match a Result<T, E> and return a Result<Json<T>, Json<E>>.
+/// Match a Result<T, E> and return a Result<Json<T>, E>.
 #[macro_export]
 macro_rules! json_result {
     ($x: expr) => {
         match $x {
             Ok(value) => return std::result::Result::Ok(rocket_contrib::json::Json(value)),
-            Err(e) => return std::result::Result::Err(rocket_contrib::json::Json(e)),
+            Err(e) => return std::result::Result::Err(e),
         };
     };
 }
@@ -0,0 +1,65 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Format a time as RFC 3339
+/// The precision is fixed, so that formatted times sort chronologically
+pub fn to_rfc3339(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Format a time as an http date(e.g. Sun, 06 Nov 1994 08:49:37 GMT)
+pub fn to_http_date(time: &DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Parse an http date
+pub fn from_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Serialization of optional times as RFC 3339 strings
+pub mod rfc3339 {
+    use chrono::{DateTime, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(time: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match time {
+            Some(value) => serializer.serialize_some(&super::to_rfc3339(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => DateTime::parse_from_rfc3339(&value)
+                .map(|time| Option::Some(time.with_timezone(&Utc)))
+                .map_err(de::Error::custom),
+            None => Result::Ok(Option::None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_http_date, to_http_date};
+
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn http_date_test() {
+        let time = Utc.ymd(1994, 11, 6).and_hms(8, 49, 37);
+
+        assert_eq!(to_http_date(&time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            from_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Option::Some(time)
+        );
+    }
+}
@@ -1,9 +1,25 @@
 use crate::common::{report::Report, DbResult};
 
-use super::model::{Feed, FeedItem};
+use super::{
+    model::{
+        aux::{ItemState, RetentionAction},
+        Feed, FeedItem,
+    },
+    query::{ItemQuery, Page},
+};
 
 use uuid::Uuid;
 
+/// Feed items looked up by their uuids
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ItemsLookup {
+    /// The items that were found, in the order of the parent feed
+    pub items: Vec<FeedItem>,
+    /// The requested uuids that do not belong to any item of the parent feed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<Uuid>,
+}
+
 /// A trait that defines the behaviour of a database connection used by feeder for feed items
 pub trait FeedItemWrapper {
     /// Create a new feed item
@@ -14,20 +30,58 @@ pub trait FeedItemWrapper {
 
     /// Get multiple feed items
     /// If no uuids are provided, all the feed items of the parent feed are returned
-    fn get_feed_items(self, parent_feed: Feed, uuids: Option<Vec<Uuid>>)
-        -> DbResult<Vec<FeedItem>>;
+    /// Items that are not published(drafts, archived or scheduled items) are treated as missing
+    /// unless they are included
+    fn get_feed_items(
+        self,
+        parent_feed: Feed,
+        uuids: Option<Vec<Uuid>>,
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup>;
+
+    /// Get a page of the feed items of the parent feed, ordered as specified by the query
+    fn list_feed_items(self, parent_feed: Feed, query: ItemQuery) -> DbResult<Page<FeedItem>>;
 
     /// Update a feed item
+    /// If a checksum is provided, the item is updated only if its stored checksum matches it
     fn update_feed_item(
         self,
         parent_feed: Feed,
         uuid: Uuid,
         feed_item: FeedItem,
+        checksum: Option<String>,
     ) -> DbResult<FeedItem>;
 
     /// Delete a feed item
-    fn delete_feed_item(self, parent_feed: Feed, uuid: Uuid) -> DbResult<Report<String>>;
+    /// If a checksum is provided, the item is deleted only if its stored checksum matches it
+    fn delete_feed_item(
+        self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        checksum: Option<String>,
+    ) -> DbResult<Report<String>>;
+
+    /// Move a feed item to another stage of its lifecycle
+    /// If a checksum is provided, the item is moved only if its stored checksum matches it
+    fn set_feed_item_state(
+        self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        state: ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<FeedItem>;
 
     /// Get the checksum of a feed item
     fn get_feed_item_checksum(self, parent_feed: Feed, uuid: Uuid) -> DbResult<String>;
+
+    /// Delete or archive some published feed items at once, refreshing the checksum of their feed
+    /// only once
+    /// The uuids of the pruned items are returned, the other uuids are not published items of the
+    /// parent feed
+    fn prune_feed_items(
+        self,
+        parent_feed: Feed,
+        uuids: Vec<Uuid>,
+        action: RetentionAction,
+    ) -> DbResult<Vec<Uuid>>;
 }
@@ -1,6 +1,9 @@
 use crate::common::{report::Report, DbResult};
 
-use super::model::Feed;
+use super::{
+    model::Feed,
+    query::{FeedQuery, Page},
+};
 
 use uuid::Uuid;
 
@@ -10,13 +13,24 @@ pub trait FeedWrapper {
     fn create_feed(self, feed: Feed) -> DbResult<Feed>;
 
     /// Get a feed from the database
+    /// Scheduled items that went live since the feed was last updated refresh its checksum and
+    /// update time on the way, even though it is a read: the validators of a feed must change as
+    /// soon as the items it lists do, which a periodic sweep could not guarantee
     fn get_feed(self, uuid: Uuid) -> DbResult<Feed>;
 
+    /// Get all the feeds from the database
+    fn get_feeds(self) -> DbResult<Vec<Feed>>;
+
+    /// Get a page of feeds, filtered and sorted as specified by the query
+    fn list_feeds(self, query: FeedQuery) -> DbResult<Page<Feed>>;
+
     /// Update a feed
-    fn update_feed(self, uuid: Uuid, feed: Feed) -> DbResult<Feed>;
+    /// If a checksum is provided, the feed is updated only if its stored checksum matches it
+    fn update_feed(self, uuid: Uuid, feed: Feed, checksum: Option<String>) -> DbResult<Feed>;
 
     /// Delete a feed
-    fn delete_feed(self, _uuid: Uuid) -> DbResult<Report<String>>;
+    /// If a checksum is provided, the feed is deleted only if its stored checksum matches it
+    fn delete_feed(self, _uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>>;
 
     /// Get the checksum of a feed
     fn get_feed_checksum(self, _uuid: Uuid) -> DbResult<String>;
@@ -1,11 +1,17 @@
 pub mod feed_item_wrapper;
 pub mod feed_wrapper;
 pub mod model;
+pub mod query;
+pub mod retention;
+pub mod storage;
 pub mod wrappers;
 
 /// Re-export wrapper traits
-pub use feed_item_wrapper::FeedItemWrapper;
+pub use feed_item_wrapper::{FeedItemWrapper, ItemsLookup};
 pub use feed_wrapper::FeedWrapper;
 
+/// Re-export the storage handle used by the router
+pub use storage::{migrations_fairing, Store};
+
 /// Re-export everything in the wrappers module
 pub use wrappers::*;
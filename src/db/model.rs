@@ -1,13 +1,17 @@
 use crate::{
-    common::errors::{Error, ModelError},
-    db::{FeedItemWrapper, FeedWrapper},
+    common::{
+        errors::{Error, ItemStateError, ModelError},
+        time,
+    },
+    db::FeedItemWrapper,
+    syndication::Format,
 };
 
-use std::sync::Arc;
-
+use chrono::{DateTime, Utc};
 use crypto::{digest::Digest, sha3::Sha3};
 use log::*;
-use mongodb::{coll::options::IndexModel, db::DatabaseInner, oid::ObjectId};
+#[cfg(feature = "mongo")]
+use mongodb::{coll::options::IndexModel, oid::ObjectId};
 use serde::Serialize;
 use uuid::Uuid;
 
@@ -15,6 +19,7 @@ const SCOPE: &str = "database/model";
 
 /// Enum that specifies whether a feed contains only
 /// the Uuids or the full items
+/// Feeds are stored without items, the Uuid variant is only read from legacy documents
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum ItemsVec {
     #[serde(rename = "uuid")]
@@ -23,8 +28,10 @@ pub enum ItemsVec {
     Full(Vec<FeedItem>),
 }
 
-#[derive(Clone, Debug, Deserialize, Model, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "mongo", derive(Model))]
 pub struct Feed {
+    #[cfg(feature = "mongo")]
     #[serde(
         rename = "_id",
         skip_serializing_if = "Option::is_none",
@@ -33,7 +40,7 @@ pub struct Feed {
     id: Option<ObjectId>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[model(index(index = "asc", unique = "true"))]
+    #[cfg_attr(feature = "mongo", model(index(index = "asc", unique = "true")))]
     uuid: Option<Uuid>,
 
     pub title: Option<String>,
@@ -49,16 +56,32 @@ pub struct Feed {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<aux::RetentionPolicy>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub items: Option<ItemsVec>,
 
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::rfc3339"
+    )]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::rfc3339"
+    )]
+    updated_at: Option<DateTime<Utc>>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     checksum: Option<String>,
 }
 
 impl Feed {
     /// Create a new feed.
-    pub fn _new(_title: &str, _description: &str, _link: &str) -> Result<Self, Error> {
+    pub fn new(_title: &str, _description: &str, _link: &str) -> Result<Self, Error> {
         debug!(
             "creating a new feed struct with args: {:?}, {:?}, {:?}",
             _title, _description, _link
@@ -69,6 +92,7 @@ impl Feed {
         let link = String::from(_link);
 
         let mut feed = Feed {
+            #[cfg(feature = "mongo")]
             id: Option::None,
             uuid: Option::Some(Uuid::new_v4()),
             title: Option::Some(title),
@@ -78,12 +102,15 @@ impl Feed {
             copyright: Option::None,
             image: Option::None,
             language: Option::None,
+            retention: Option::None,
             items: Option::None,
+            created_at: Option::None,
+            updated_at: Option::None,
             checksum: Option::None,
         };
 
         // compute the checksum
-        if let Some(err) = feed.compute_checksum(Option::None) {
+        if let Some(err) = feed.compute_checksum() {
             error!("checksum could not computed");
             return Result::Err(err);
         }
@@ -95,20 +122,12 @@ impl Feed {
     /// Create a new feed fromma given model
     pub fn new_from_model(model: Feed) -> Result<Self, Error> {
         // Filter out bad models
-        if model.title.is_none() {
-            warn!("{}", ModelError::ModelHasNoTitle);
-            return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoTitle));
-        }
-        if model.description.is_none() {
-            warn!("{}", ModelError::ModelHasNoDescription);
-            return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoDescription));
-        }
-        if model.link.is_none() {
-            warn!("{}", ModelError::ModelHasNoLink);
-            return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoLink));
+        if let Some(e) = model.validate() {
+            return Result::Err(e);
         }
 
         let mut feed = Feed {
+            #[cfg(feature = "mongo")]
             id: Option::None,
             uuid: Option::Some(Uuid::new_v4()),
             title: model.title,
@@ -118,12 +137,15 @@ impl Feed {
             copyright: model.copyright,
             image: model.image,
             language: model.language,
+            retention: model.retention,
             items: Option::None,
+            created_at: Option::None,
+            updated_at: Option::None,
             checksum: Option::None,
         };
 
         // Compute the checksum
-        if let Some(err) = feed.compute_checksum(Option::None) {
+        if let Some(err) = feed.compute_checksum() {
             error!("could not compute the checksum for this feed");
             return Result::Err(err);
         }
@@ -131,107 +153,141 @@ impl Feed {
         Result::Ok(feed)
     }
 
+    /// Check that this feed has all the required fields
+    pub fn validate(&self) -> Option<Error> {
+        validate_required_fields(&self.title, &self.description, &self.link)
+    }
+
+    /// Get the time this feed was created at
+    pub fn get_created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    /// Get the time this feed or one of its items was last changed at
+    pub fn get_updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    /// Set the time this feed was created at, which is also the first time it was changed at
+    pub fn set_created_at(&mut self, time: DateTime<Utc>) {
+        self.created_at = Option::Some(time);
+        self.updated_at = Option::Some(time);
+    }
+
+    /// Set the time this feed was last changed at
+    pub fn set_updated_at(&mut self, time: DateTime<Utc>) {
+        self.updated_at = Option::Some(time);
+    }
+
+    /// Keep the fields managed by feeder(the uuid and the creation time) of the stored version
+    /// of this feed, which is replaced by this one
+    pub fn keep_managed_fields(&mut self, stored: &Feed) {
+        self.uuid = stored.uuid;
+        self.created_at = stored.created_at;
+    }
+
     /// Get the UUID of this feed
     pub fn get_uuid(&self) -> Option<Uuid> {
         self.uuid.clone()
     }
 
+    /// Set the UUID of this feed, for models received without it
+    pub fn set_uuid(&mut self, uuid: Uuid) {
+        self.uuid = Option::Some(uuid);
+    }
+
     /// Get the checksum of this feed
     pub fn get_checksum(&self) -> Option<String> {
         self.checksum.clone()
     }
 
-    /// Compute the checksum of this feed
+    /// Compute the checksum of this feed, covering the items it holds
     /// The checksum is saved inside the object
-    pub fn compute_checksum(&mut self, db_conn: Option<Arc<DatabaseInner>>) -> Option<Error> {
+    pub fn compute_checksum(&mut self) -> Option<Error> {
         debug!("computing checksum for feed {:?}", self);
 
-        let change_flag: bool = if let Some(value) = db_conn {
-            self.with_items(value);
-            true
-        } else {
-            false
-        };
-        let result: Option<Error>;
+        // The checksum only covers the content, not the times managed by feeder
+        let mut model: Feed = self.clone();
+        model.created_at = Option::None;
+        model.updated_at = Option::None;
+        if let Some(ItemsVec::Full(items)) = &mut model.items {
+            for item in items.iter_mut() {
+                item.created_at = Option::None;
+                item.updated_at = Option::None;
+            }
+        }
 
-        match compute_checksum(self) {
+        match compute_checksum(&mut model) {
             Ok(checksum) => {
                 self.checksum = Option::Some(checksum);
-                result = Option::None;
+                Option::None
             }
-            Err(e) => result = Option::Some(e),
+            Err(e) => Option::Some(e),
         }
+    }
 
-        if change_flag {
-            self.with_uuids();
+    /// Compute the checksum of this feed, covering its published items as well
+    /// The checksum changes when a scheduled item goes live
+    pub fn compute_checksum_with_items<W>(&mut self, db_conn: W) -> Option<Error>
+    where
+        W: FeedItemWrapper,
+    {
+        let mut model: Feed = self.clone();
+        if let Some(e) = model.with_items(db_conn) {
+            return Option::Some(e);
+        }
+        if let Some(e) = model.compute_checksum() {
+            return Option::Some(e);
         }
 
-        result
+        self.checksum = model.checksum;
+        Option::None
     }
 
-    /// Return this feed along with its items
-    pub fn with_items(&mut self, db_conn: Arc<DatabaseInner>) -> Option<Error> {
-        if self.items.is_none() {
-            self.items = Option::Some(ItemsVec::Uuid(Vec::new()));
-            match db_conn
-                .clone()
-                .update_feed(self.get_uuid().unwrap(), self.clone())
-            {
-                Ok(_) => return Option::None,
-                Err(e) => return Option::Some(e),
-            }
+    /// Return this feed along with its published items
+    pub fn with_items<W>(&mut self, db_conn: W) -> Option<Error>
+    where
+        W: FeedItemWrapper,
+    {
+        if let Some(ItemsVec::Full(_)) = self.items {
+            return Option::None;
         }
 
-        match self.items.clone().unwrap() {
-            ItemsVec::Full(_) => Option::None,
-            ItemsVec::Uuid(items_uuid) => {
-                let items_full: Vec<FeedItem>;
-                match db_conn.get_feed_items(self.clone(), Option::Some(items_uuid)) {
-                    Ok(value) => items_full = value,
-                    Err(e) => return Option::Some(e),
-                };
-                self.items = Option::Some(ItemsVec::Full(items_full));
+        match db_conn.get_feed_items(self.clone(), Option::None, false) {
+            Ok(value) => {
+                self.items = Option::Some(ItemsVec::Full(value.items));
                 Option::None
             }
+            Err(e) => Option::Some(e),
         }
     }
 
-    /// Return this feed with the items having only uuids
-    pub fn with_uuids(&mut self) {
-        // If there are no items to be converted, return
-        if self.items.is_none() {
-            return;
-        }
-
-        let items_vec: ItemsVec = self.clone().items.unwrap();
-        match items_vec {
-            ItemsVec::Uuid(_) => {}
-            ItemsVec::Full(items) => {
-                let mut items_uuid: Vec<Uuid> = Vec::new();
-
-                for item in items {
-                    if item.get_uuid().is_none() {
-                        continue;
-                    }
-                    items_uuid.push(item.get_uuid().unwrap());
-                }
-
-                self.items = Option::Some(ItemsVec::Uuid(items_uuid));
-            }
+    /// Generate the representation of this feed in a syndication format(rss, atom, json feed).
+    pub fn generate<W>(&mut self, db_conn: W, format: Format) -> Result<String, Error>
+    where
+        W: FeedItemWrapper,
+    {
+        if let Some(e) = self.with_items(db_conn) {
+            warn!("failed to get the items of the feed before rendering it");
+            return Result::Err(e);
         }
-    }
 
-    /// Generate the RSS representation of this feed.
-    pub fn _generate_rss(&self, _db_conn: Arc<DatabaseInner>) {
-        unimplemented!();
+        Result::Ok(format.render(self))
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Model, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "mongo", derive(Model))]
 pub struct FeedItem {
+    #[cfg(feature = "mongo")]
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none", skip)]
-    id: Option<mongodb::oid::ObjectId>,
+    id: Option<ObjectId>,
     uuid: Option<Uuid>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "mongo", model(index(index = "asc")))]
+    feed_uuid: Option<Uuid>,
+
     pub title: Option<String>,
     pub link: Option<String>,
     pub description: Option<String>,
@@ -242,6 +298,29 @@ pub struct FeedItem {
     pub comments: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enclosure: Option<aux::FeedItemEnclosure>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::rfc3339"
+    )]
+    pub published_at: Option<DateTime<Utc>>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::rfc3339"
+    )]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::rfc3339"
+    )]
+    updated_at: Option<DateTime<Utc>>,
+
+    /// Items stored before their lifecycle existed are published
+    #[serde(default)]
+    state: aux::ItemState,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     checksum: Option<String>,
@@ -249,20 +328,26 @@ pub struct FeedItem {
 
 impl FeedItem {
     /// Create a new feed item
-    pub fn _new(_title: &str, _link: &str, _description: &str) -> Result<Self, Error> {
+    pub fn new(_title: &str, _link: &str, _description: &str) -> Result<Self, Error> {
         let title = String::from(_title);
         let link = String::from(_link);
         let description = String::from(_description);
 
         let mut feed_item = FeedItem {
+            #[cfg(feature = "mongo")]
             id: Option::None,
             uuid: Option::Some(Uuid::new_v4()),
+            feed_uuid: Option::None,
             title: Option::Some(title),
             link: Option::Some(link),
             description: Option::Some(description),
             author: Option::None,
             comments: Option::None,
             enclosure: Option::None,
+            published_at: Option::None,
+            created_at: Option::None,
+            updated_at: Option::None,
+            state: aux::ItemState::default(),
             checksum: Option::None,
         };
 
@@ -275,28 +360,37 @@ impl FeedItem {
 
     pub fn new_from_model(model: FeedItem) -> Result<Self, Error> {
         // Filter out bad models
-        if model.title.is_none() {
-            warn!("{}", ModelError::ModelHasNoTitle);
-            return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoTitle));
-        }
-        if model.description.is_none() {
-            warn!("{}", ModelError::ModelHasNoDescription);
-            return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoDescription));
+        if let Some(e) = model.validate() {
+            return Result::Err(e);
         }
-        if model.link.is_none() {
-            warn!("{}", ModelError::ModelHasNoLink);
-            return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoLink));
+        // Items are only archived through transitions
+        if model.state == aux::ItemState::Archived {
+            warn!("items cannot be created {}", model.state);
+            return Result::Err(create_error!(
+                SCOPE,
+                ModelError::InvalidInitialState {
+                    state: format!("{}", model.state),
+                }
+            ));
         }
 
         let mut feed_item: FeedItem = FeedItem {
+            #[cfg(feature = "mongo")]
             id: Option::None,
             uuid: Option::Some(Uuid::new_v4()),
+            feed_uuid: Option::None,
             title: model.title,
             link: model.link,
             description: model.description,
             author: model.author,
             comments: model.comments,
             enclosure: model.enclosure,
+            // Items that are not given a publication time are published when they are created
+            published_at: model.published_at.or_else(|| Option::Some(Utc::now())),
+            created_at: Option::None,
+            updated_at: Option::None,
+            // Items can be created as drafts, afterwards they change state through transitions
+            state: model.state,
             checksum: Option::None,
         };
 
@@ -307,9 +401,76 @@ impl FeedItem {
         Result::Ok(feed_item)
     }
 
+    /// Check that this feed item has all the required fields
+    pub fn validate(&self) -> Option<Error> {
+        validate_required_fields(&self.title, &self.description, &self.link)
+    }
+
+    /// Get the time this feed item was created at
+    pub fn get_created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    /// Get the time this feed item was last changed at
+    pub fn get_updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    /// Set the time this feed item was created at, which is also the first time it was changed at
+    pub fn set_created_at(&mut self, time: DateTime<Utc>) {
+        self.created_at = Option::Some(time);
+        self.updated_at = Option::Some(time);
+    }
+
+    /// Set the time this feed item was last changed at
+    pub fn set_updated_at(&mut self, time: DateTime<Utc>) {
+        self.updated_at = Option::Some(time);
+    }
+
+    /// Get the stage of its lifecycle this feed item is in
+    pub fn get_state(&self) -> aux::ItemState {
+        self.state
+    }
+
+    /// Move this feed item to another stage of its lifecycle
+    /// Drafts and archived items can be published, and only published items can be archived
+    /// Drafts are published at their publication time if it is still to come, or else right away
+    pub fn transition(&mut self, state: aux::ItemState) -> Option<Error> {
+        match (self.state, state) {
+            (aux::ItemState::Draft, aux::ItemState::Published) => {
+                let now: DateTime<Utc> = Utc::now();
+                match self.published_at {
+                    Some(value) if value > now => {}
+                    _ => self.published_at = Option::Some(now),
+                }
+            }
+            (aux::ItemState::Archived, aux::ItemState::Published)
+            | (aux::ItemState::Published, aux::ItemState::Archived) => {}
+            (from, to) => {
+                warn!("invalid transition of a feed item from {} to {}", from, to);
+                return Option::Some(create_error!(
+                    SCOPE,
+                    ItemStateError::InvalidTransition {
+                        from: format!("{}", from),
+                        to: format!("{}", to),
+                    }
+                ));
+            }
+        }
+
+        // The checksum covers the state, so it changes along with it
+        self.state = state;
+        self.compute_checksum()
+    }
+
     /// Compute the checksum for this feed item
+    /// The checksum covers the content and the state, not the times managed by feeder
     pub fn compute_checksum(&mut self) -> Option<Error> {
-        match compute_checksum(self) {
+        let mut model: FeedItem = self.clone();
+        model.created_at = Option::None;
+        model.updated_at = Option::None;
+
+        match compute_checksum(&mut model) {
             Ok(value) => {
                 self.checksum = Option::Some(value);
                 Option::None
@@ -327,6 +488,50 @@ impl FeedItem {
     pub fn get_checksum(&self) -> Option<String> {
         self.checksum.clone()
     }
+
+    /// Get the uuid of the feed this item belongs to
+    pub fn get_feed_uuid(&self) -> Option<Uuid> {
+        self.feed_uuid
+    }
+
+    /// Set the uuid of the feed this item belongs to
+    pub fn set_feed_uuid(&mut self, feed_uuid: Uuid) {
+        self.feed_uuid = Option::Some(feed_uuid);
+    }
+
+    /// Keep the fields managed by feeder(the uuids, the creation time and the state) of the
+    /// stored version of this feed item, which is replaced by this one
+    /// The publication time set when the item was created is kept too, unless this one has another
+    pub fn keep_managed_fields(&mut self, stored: &FeedItem) {
+        self.uuid = stored.uuid;
+        self.feed_uuid = stored.feed_uuid;
+        self.created_at = stored.created_at;
+        self.state = stored.state;
+        if self.published_at.is_none() {
+            self.published_at = stored.published_at;
+        }
+    }
+}
+
+/// Check the fields required by both feeds and feed items
+fn validate_required_fields(
+    title: &Option<String>,
+    description: &Option<String>,
+    link: &Option<String>,
+) -> Option<Error> {
+    if title.is_none() {
+        warn!("{}", ModelError::ModelHasNoTitle);
+        return Option::Some(create_error!(SCOPE, ModelError::ModelHasNoTitle));
+    }
+    if description.is_none() {
+        warn!("{}", ModelError::ModelHasNoDescription);
+        return Option::Some(create_error!(SCOPE, ModelError::ModelHasNoDescription));
+    }
+    if link.is_none() {
+        warn!("{}", ModelError::ModelHasNoLink);
+        return Option::Some(create_error!(SCOPE, ModelError::ModelHasNoLink));
+    }
+    Option::None
 }
 
 /// Compute the checksum for a given model
@@ -346,31 +551,150 @@ where
 
 /// Module that contains auxiliary models
 pub mod aux {
+    use super::FeedItem;
+
+    use crate::common::errors::{Error, QueryError};
+
+    use std::{fmt, str::FromStr};
+
+    use chrono::{DateTime, Duration, Utc};
+    use uuid::Uuid;
+
+    const SCOPE: &str = "database/model/aux";
+
+    /// The stages of the lifecycle of a feed item
+    /// Only published items are syndicated and covered by the checksum of their feed
+    #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ItemState {
+        Draft,
+        Published,
+        Archived,
+    }
+
+    impl Default for ItemState {
+        fn default() -> Self {
+            ItemState::Published
+        }
+    }
+
+    impl fmt::Display for ItemState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ItemState::Draft => write!(f, "draft"),
+                ItemState::Published => write!(f, "published"),
+                ItemState::Archived => write!(f, "archived"),
+            }
+        }
+    }
+
+    impl FromStr for ItemState {
+        type Err = Error;
+
+        /// Parse a state query parameter: "draft", "published" or "archived"
+        fn from_str(value: &str) -> Result<Self, Self::Err> {
+            match value {
+                "draft" => Result::Ok(ItemState::Draft),
+                "published" => Result::Ok(ItemState::Published),
+                "archived" => Result::Ok(ItemState::Archived),
+                _ => Result::Err(create_error!(
+                    SCOPE,
+                    QueryError::InvalidState {
+                        state: value.to_string()
+                    }
+                )),
+            }
+        }
+    }
+
     #[derive(Clone, Debug, Deserialize, Serialize)]
     pub struct FeedItemEnclosure {
-        url: String,
-        length: String,
-        _type: String,
+        pub url: String,
+        pub length: String,
+        pub _type: String,
     }
 
     #[derive(Clone, Debug, Deserialize, Serialize)]
     pub struct FeedImage {
-        url: String,
-        title: String,
-        link: String,
+        pub url: String,
+        pub title: String,
+        pub link: String,
+    }
+
+    /// What happens to the items of a feed that fall out of its retention policy
+    #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum RetentionAction {
+        Delete,
+        Archive,
+    }
+
+    impl Default for RetentionAction {
+        fn default() -> Self {
+            RetentionAction::Delete
+        }
+    }
+
+    /// The retention policy of the items of a feed
+    /// Only published items are subject to it, newer items are kept first
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct RetentionPolicy {
+        /// The maximum number of items kept
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_items: Option<u32>,
+        /// The maximum age of the items kept, in days since their publication
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_age_days: Option<u32>,
+        #[serde(default)]
+        pub action: RetentionAction,
+    }
+
+    impl RetentionPolicy {
+        /// Get the uuids of the items that fall out of this policy
+        /// Items without a publication time are only subject to the maximum number of items
+        pub fn expired_items(&self, items: &[FeedItem], now: DateTime<Utc>) -> Vec<Uuid> {
+            let mut sorted: Vec<&FeedItem> = items.iter().collect();
+            sorted.sort_by(|a, b| b.published_at.cmp(&a.published_at));
+
+            let oldest: Option<DateTime<Utc>> = self
+                .max_age_days
+                .map(|days| now - Duration::days(i64::from(days)));
+
+            sorted
+                .iter()
+                .enumerate()
+                .filter(|(index, item)| {
+                    let too_many: bool = match self.max_items {
+                        Some(max) => *index >= max as usize,
+                        None => false,
+                    };
+                    let too_old: bool = match (oldest, item.published_at) {
+                        (Some(limit), Some(published_at)) => published_at < limit,
+                        _ => false,
+                    };
+                    too_many || too_old
+                })
+                .filter_map(|(_, item)| item.get_uuid())
+                .collect()
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Feed;
+    use super::{
+        aux::{ItemState, RetentionAction, RetentionPolicy},
+        Feed, FeedItem,
+    };
+
+    use chrono::{Duration, Utc};
 
     #[test]
     fn feed_new_test() {
         #![allow(warnings)]
         *crate::_LOG;
 
-        let _feed = Feed::_new(
+        let _feed = Feed::new(
             "My title",
             "My example description for my feed test",
             "https://example.com",
@@ -384,4 +708,52 @@ mod test {
         println!("uuid: {}", feed.uuid.unwrap());
         println!("checksum: {}", feed.checksum.unwrap());
     }
+
+    #[test]
+    fn feed_item_transition_test() {
+        let mut feed_item = FeedItem::new("Title", "https://example.com/1", "Description").unwrap();
+        let checksum = feed_item.get_checksum();
+
+        // The checksum covers the state along with the content
+        assert!(feed_item.transition(ItemState::Archived).is_none());
+        assert_ne!(feed_item.get_checksum(), checksum);
+        assert!(feed_item.transition(ItemState::Archived).is_some());
+        assert!(feed_item.transition(ItemState::Published).is_none());
+        assert_eq!(feed_item.get_state(), ItemState::Published);
+        assert_eq!(feed_item.get_checksum(), checksum);
+
+        // Items are only archived through transitions
+        feed_item.state = ItemState::Archived;
+        let result = FeedItem::new_from_model(feed_item);
+        assert_eq!(result.unwrap_err().code(), "state_invalid");
+    }
+
+    #[test]
+    fn retention_policy_test() {
+        let now = Utc::now();
+        let mut items: Vec<FeedItem> = Vec::new();
+        for days in 0..4 {
+            let mut feed_item =
+                FeedItem::new("Title", "https://example.com/1", "Description").unwrap();
+            feed_item.published_at = Option::Some(now - Duration::days(days));
+            items.push(feed_item);
+        }
+
+        let by_count = RetentionPolicy {
+            max_items: Option::Some(3),
+            max_age_days: Option::None,
+            action: RetentionAction::Delete,
+        };
+        assert_eq!(
+            by_count.expired_items(&items, now),
+            vec![items[3].get_uuid().unwrap()]
+        );
+
+        let by_age = RetentionPolicy {
+            max_items: Option::None,
+            max_age_days: Option::Some(1),
+            action: RetentionAction::Archive,
+        };
+        assert_eq!(by_age.expired_items(&items, now).len(), 2);
+    }
 }
@@ -0,0 +1,459 @@
+use crate::{
+    common::{errors::QueryError, DbResult},
+    db::model::{aux::ItemState, FeedItem},
+};
+
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+const SCOPE: &str = "database/query";
+
+/// The default number of results in a page
+pub const DEFAULT_LIMIT: u32 = 50;
+/// The maximum number of results in a page
+pub const MAX_LIMIT: u32 = 500;
+
+/// A page of results
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The cursor of the next page, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Create a page out of the results fetched for a query
+    /// The results are expected to contain one extra element if there is a next page
+    /// The cursor is the offset of the next page
+    pub fn from_results(mut results: Vec<T>, offset: u64, limit: u32) -> Self {
+        let mut next: Option<String> = Option::None;
+        if results.len() > limit as usize {
+            results.truncate(limit as usize);
+            next = Option::Some(format!("{}", offset + u64::from(limit)));
+        }
+
+        Page {
+            items: results,
+            next,
+        }
+    }
+}
+
+impl Page<FeedItem> {
+    /// Create a page of feed items out of the results fetched after a cursor
+    /// The results are expected to contain one extra item if there is a next page
+    /// The cursor is the position of the last item of the page, so the next page starts right
+    /// after it even if items are created or deleted in the meantime
+    pub fn from_items(mut results: Vec<FeedItem>, limit: u32) -> Self {
+        let mut next: Option<String> = Option::None;
+        if results.len() > limit as usize {
+            results.truncate(limit as usize);
+            next = results
+                .last()
+                .and_then(ItemCursor::of)
+                .map(|cursor| format!("{}", cursor));
+        }
+
+        Page {
+            items: results,
+            next,
+        }
+    }
+}
+
+/// Check the number of results in a page received by the router
+fn check_limit(limit: Option<u32>) -> DbResult<u32> {
+    let good_limit: u32 = limit.unwrap_or(DEFAULT_LIMIT);
+    if good_limit == 0 || good_limit > MAX_LIMIT {
+        return Result::Err(create_error!(
+            SCOPE,
+            QueryError::InvalidLimit { max: MAX_LIMIT }
+        ));
+    }
+    Result::Ok(good_limit)
+}
+
+/// The pagination of a query of feeds
+/// It is offset based: the cursor is the number of feeds that come before the page
+#[derive(Clone, Copy, Debug)]
+pub struct Pagination {
+    pub offset: u64,
+    pub limit: u32,
+}
+
+impl Pagination {
+    /// Create the pagination of a query from the limit and cursor received by the router
+    /// Offsets are at most i64::MAX, which is the most the databases skip
+    pub fn new(limit: Option<u32>, cursor: Option<String>) -> DbResult<Self> {
+        let good_limit: u32 = check_limit(limit)?;
+
+        let offset: u64 = match cursor {
+            Some(value) => match u64::from_str(value.as_str()) {
+                Ok(_value) if _value <= i64::max_value() as u64 => _value,
+                _ => return Result::Err(create_error!(SCOPE, QueryError::InvalidCursor)),
+            },
+            None => 0,
+        };
+
+        Result::Ok(Pagination {
+            offset,
+            limit: good_limit,
+        })
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination {
+            offset: 0,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+/// The ways feeds can be sorted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeedSort {
+    TitleAscending,
+    TitleDescending,
+    CreatedAscending,
+    CreatedDescending,
+}
+
+impl FromStr for FeedSort {
+    type Err = crate::common::errors::Error;
+
+    /// Parse a sort query parameter: "title" or "created", prefixed by "-" for descending order
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "title" => Result::Ok(FeedSort::TitleAscending),
+            "-title" => Result::Ok(FeedSort::TitleDescending),
+            "created" => Result::Ok(FeedSort::CreatedAscending),
+            "-created" => Result::Ok(FeedSort::CreatedDescending),
+            _ => Result::Err(create_error!(
+                SCOPE,
+                QueryError::InvalidSort {
+                    sort: value.to_string()
+                }
+            )),
+        }
+    }
+}
+
+impl Default for FeedSort {
+    fn default() -> Self {
+        FeedSort::CreatedAscending
+    }
+}
+
+/// A query for listing feeds
+#[derive(Clone, Debug, Default)]
+pub struct FeedQuery {
+    pub pagination: Pagination,
+    pub sort: FeedSort,
+    pub category: Option<String>,
+    pub language: Option<String>,
+}
+
+/// The orders feed items can be listed in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemOrder {
+    NewestFirst,
+    OldestFirst,
+}
+
+impl FromStr for ItemOrder {
+    type Err = crate::common::errors::Error;
+
+    /// Parse an order query parameter: "newest" or "oldest"
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "newest" => Result::Ok(ItemOrder::NewestFirst),
+            "oldest" => Result::Ok(ItemOrder::OldestFirst),
+            _ => Result::Err(create_error!(
+                SCOPE,
+                QueryError::InvalidOrder {
+                    order: value.to_string()
+                }
+            )),
+        }
+    }
+}
+
+impl Default for ItemOrder {
+    fn default() -> Self {
+        ItemOrder::NewestFirst
+    }
+}
+
+/// The position of a feed item in the listings of its feed
+/// Items are listed by publication time, the items without one come first in chronological order,
+/// and then by uuid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ItemCursor {
+    pub published_at: Option<DateTime<Utc>>,
+    pub uuid: Uuid,
+}
+
+impl ItemCursor {
+    /// Get the position of a feed item
+    pub fn of(feed_item: &FeedItem) -> Option<Self> {
+        feed_item.get_uuid().map(|uuid| ItemCursor {
+            published_at: feed_item.published_at,
+            uuid,
+        })
+    }
+
+    /// Check if a feed item comes after this position in the given order
+    pub fn precedes(&self, feed_item: &FeedItem, order: ItemOrder) -> bool {
+        let position: (Option<DateTime<Utc>>, Option<Uuid>) =
+            (feed_item.published_at, feed_item.get_uuid());
+        let cursor: (Option<DateTime<Utc>>, Option<Uuid>) =
+            (self.published_at, Option::Some(self.uuid));
+        match order {
+            ItemOrder::NewestFirst => position < cursor,
+            ItemOrder::OldestFirst => position > cursor,
+        }
+    }
+}
+
+impl fmt::Display for ItemCursor {
+    /// Format the position as the publication time(with its full precision, empty if there is
+    /// none) and the uuid, separated by an underscore
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.published_at {
+            Some(value) => write!(
+                f,
+                "{}_{}",
+                value.to_rfc3339_opts(SecondsFormat::Nanos, true),
+                self.uuid
+            ),
+            None => write!(f, "_{}", self.uuid),
+        }
+    }
+}
+
+impl FromStr for ItemCursor {
+    type Err = crate::common::errors::Error;
+
+    /// Parse a cursor formatted by Page::from_items
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.splitn(2, '_');
+        let published_at: Option<DateTime<Utc>> = match parts.next() {
+            Some("") => Option::None,
+            Some(part) => match DateTime::parse_from_rfc3339(part) {
+                Ok(_value) => Option::Some(_value.with_timezone(&Utc)),
+                Err(_) => return Result::Err(create_error!(SCOPE, QueryError::InvalidCursor)),
+            },
+            None => return Result::Err(create_error!(SCOPE, QueryError::InvalidCursor)),
+        };
+        match parts.next().map(Uuid::parse_str) {
+            Some(Ok(uuid)) => Result::Ok(ItemCursor { published_at, uuid }),
+            _ => Result::Err(create_error!(SCOPE, QueryError::InvalidCursor)),
+        }
+    }
+}
+
+/// The pagination of a query of feed items
+/// It is keyset based: the cursor is the position of the last item of the previous page
+#[derive(Clone, Copy, Debug)]
+pub struct ItemPagination {
+    pub after: Option<ItemCursor>,
+    pub limit: u32,
+}
+
+impl ItemPagination {
+    /// Create the pagination of a query from the limit and cursor received by the router
+    pub fn new(limit: Option<u32>, cursor: Option<String>) -> DbResult<Self> {
+        let good_limit: u32 = check_limit(limit)?;
+
+        let after: Option<ItemCursor> = match cursor {
+            Some(value) => Option::Some(ItemCursor::from_str(value.as_str())?),
+            None => Option::None,
+        };
+
+        Result::Ok(ItemPagination {
+            after,
+            limit: good_limit,
+        })
+    }
+}
+
+impl Default for ItemPagination {
+    fn default() -> Self {
+        ItemPagination {
+            after: Option::None,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+/// A query for listing the items of a feed
+#[derive(Clone, Debug, Default)]
+pub struct ItemQuery {
+    pub pagination: ItemPagination,
+    pub order: ItemOrder,
+    /// The state of the listed items
+    pub state: ItemState,
+    /// Whether published items scheduled for a later publication are listed too
+    pub include_scheduled: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        FeedSort, ItemCursor, ItemOrder, ItemPagination, Page, Pagination, DEFAULT_LIMIT, MAX_LIMIT,
+    };
+
+    use crate::db::model::FeedItem;
+
+    use std::str::FromStr;
+
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    #[test]
+    fn pagination_test() {
+        let pagination: Pagination = Pagination::new(Option::None, Option::None).unwrap();
+        assert_eq!(pagination.offset, 0);
+        assert_eq!(pagination.limit, DEFAULT_LIMIT);
+
+        let pagination: Pagination =
+            Pagination::new(Option::Some(MAX_LIMIT), Option::Some("20".to_string())).unwrap();
+        assert_eq!(pagination.offset, 20);
+        assert_eq!(pagination.limit, MAX_LIMIT);
+    }
+
+    #[test]
+    fn invalid_pagination_test() {
+        let e = Pagination::new(Option::Some(0), Option::None).unwrap_err();
+        assert_eq!(e.code(), "limit_invalid");
+
+        let e = Pagination::new(Option::Some(MAX_LIMIT + 1), Option::None).unwrap_err();
+        assert_eq!(e.code(), "limit_invalid");
+
+        let e = Pagination::new(Option::None, Option::Some("next".to_string())).unwrap_err();
+        assert_eq!(e.code(), "cursor_invalid");
+
+        let e = Pagination::new(Option::None, Option::Some("-1".to_string())).unwrap_err();
+        assert_eq!(e.code(), "cursor_invalid");
+
+        // Larger offsets cannot be skipped by the databases
+        let e = Pagination::new(Option::None, Option::Some(format!("{}", u64::max_value())))
+            .unwrap_err();
+        assert_eq!(e.code(), "cursor_invalid");
+    }
+
+    #[test]
+    fn item_cursor_test() {
+        let cursor: ItemCursor = ItemCursor {
+            published_at: Option::Some(Utc.ymd(2020, 1, 2).and_hms_nano(3, 4, 5, 123_456_789)),
+            uuid: Uuid::new_v4(),
+        };
+        assert_eq!(
+            ItemCursor::from_str(&format!("{}", cursor)).unwrap(),
+            cursor
+        );
+
+        let cursor: ItemCursor = ItemCursor {
+            published_at: Option::None,
+            uuid: Uuid::new_v4(),
+        };
+        assert_eq!(
+            ItemCursor::from_str(&format!("{}", cursor)).unwrap(),
+            cursor
+        );
+
+        for value in &[
+            "",
+            "20",
+            "2020-01-02T03:04:05Z",
+            "2020-01-02T03:04:05Z_next",
+        ] {
+            let e = ItemPagination::new(Option::None, Option::Some(value.to_string())).unwrap_err();
+            assert_eq!(e.code(), "cursor_invalid");
+        }
+    }
+
+    #[test]
+    fn item_page_test() {
+        let mut results: Vec<FeedItem> = Vec::new();
+        for hour in 0..3 {
+            let mut feed_item: FeedItem = FeedItem::new_from_model(
+                FeedItem::new("Title", "https://example.com/item", "Description").unwrap(),
+            )
+            .unwrap();
+            feed_item.published_at = Option::Some(Utc.ymd(2020, 1, 1).and_hms(hour, 0, 0));
+            results.push(feed_item);
+        }
+
+        // The next page starts after the last item of the page
+        let page: Page<FeedItem> = Page::from_items(results.clone(), 2);
+        assert_eq!(page.items.len(), 2);
+        let cursor: ItemCursor = ItemCursor::from_str(&page.next.unwrap()).unwrap();
+        assert_eq!(cursor, ItemCursor::of(&results[1]).unwrap());
+        assert!(cursor.precedes(&results[2], ItemOrder::OldestFirst));
+        assert!(!cursor.precedes(&results[1], ItemOrder::OldestFirst));
+        assert!(cursor.precedes(&results[0], ItemOrder::NewestFirst));
+
+        let page: Page<FeedItem> = Page::from_items(results, 3);
+        assert_eq!(page.next, Option::None);
+    }
+
+    #[test]
+    fn page_test() {
+        let page: Page<u32> = Page::from_results(vec![1, 2, 3], 10, 2);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next, Option::Some("12".to_string()));
+
+        let page: Page<u32> = Page::from_results(vec![1, 2], 10, 2);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next, Option::None);
+    }
+
+    #[test]
+    fn feed_sort_test() {
+        assert_eq!(
+            FeedSort::from_str("title").unwrap(),
+            FeedSort::TitleAscending
+        );
+        assert_eq!(
+            FeedSort::from_str("-title").unwrap(),
+            FeedSort::TitleDescending
+        );
+        assert_eq!(
+            FeedSort::from_str("created").unwrap(),
+            FeedSort::CreatedAscending
+        );
+        assert_eq!(
+            FeedSort::from_str("-created").unwrap(),
+            FeedSort::CreatedDescending
+        );
+
+        assert_eq!(
+            FeedSort::from_str("Title").unwrap_err().code(),
+            "sort_invalid"
+        );
+        assert_eq!(FeedSort::from_str("").unwrap_err().code(), "sort_invalid");
+    }
+
+    #[test]
+    fn item_order_test() {
+        assert_eq!(
+            ItemOrder::from_str("newest").unwrap(),
+            ItemOrder::NewestFirst
+        );
+        assert_eq!(
+            ItemOrder::from_str("oldest").unwrap(),
+            ItemOrder::OldestFirst
+        );
+
+        assert_eq!(
+            ItemOrder::from_str("-newest").unwrap_err().code(),
+            "order_invalid"
+        );
+    }
+}
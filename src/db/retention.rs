@@ -0,0 +1,218 @@
+use crate::common::{report::Report, DbResult};
+
+use super::{
+    model::{
+        aux::{RetentionAction, RetentionPolicy},
+        Feed,
+    },
+    FeedItemWrapper, FeedWrapper, ItemsLookup, Store,
+};
+
+use std::{thread, time::Duration};
+
+use chrono::Utc;
+use log::*;
+use rocket::fairing::AdHoc;
+use uuid::Uuid;
+
+const SCOPE: &str = "database/retention";
+
+/// The default number of seconds between two sweeps
+const DEFAULT_SWEEP_INTERVAL: i64 = 3600;
+
+/// Summary of the pruning of a feed
+#[derive(Clone, Debug, Serialize)]
+pub struct PruneSummary {
+    pub feed: Option<Uuid>,
+    pub action: RetentionAction,
+    pub pruned: Vec<Uuid>,
+    pub errors: Vec<String>,
+}
+
+/// Delete or archive the items of a feed that fall out of its retention policy
+pub fn prune<W>(db_conn: W, feed: Feed) -> DbResult<PruneSummary>
+where
+    W: FeedItemWrapper + Clone,
+{
+    let mut summary: PruneSummary = PruneSummary {
+        feed: feed.get_uuid(),
+        action: RetentionAction::default(),
+        pruned: Vec::new(),
+        errors: Vec::new(),
+    };
+    let policy: RetentionPolicy;
+    match &feed.retention {
+        Some(value) => policy = value.clone(),
+        None => {
+            info!("the feed has no retention policy");
+            return Result::Ok(summary);
+        }
+    }
+    summary.action = policy.action;
+
+    let items: ItemsLookup = db_conn
+        .clone()
+        .get_feed_items(feed.clone(), Option::None, false)?;
+    let expired: Vec<Uuid> = policy.expired_items(&items.items, Utc::now());
+    if expired.is_empty() {
+        return Result::Ok(summary);
+    }
+
+    // The expired items are pruned at once, so the checksum of the feed is refreshed only once
+    match db_conn.prune_feed_items(feed, expired, policy.action) {
+        Ok(value) => summary.pruned = value,
+        Err(e) => {
+            warn!("failed to prune the feed items: {}", e);
+            summary.errors.push(format!("{}", e));
+        }
+    }
+
+    Result::Ok(summary)
+}
+
+/// Prune all the feeds that have a retention policy
+pub fn sweep<W>(db_conn: W) -> DbResult<Report<Vec<PruneSummary>>>
+where
+    W: FeedWrapper + FeedItemWrapper + Clone,
+{
+    let mut summaries: Vec<PruneSummary> = Vec::new();
+    for feed in db_conn.clone().get_feeds()? {
+        if feed.retention.is_none() {
+            continue;
+        }
+
+        match prune(db_conn.clone(), feed) {
+            Ok(summary) => summaries.push(summary),
+            Err(e) => warn!("failed to prune a feed: {}", e),
+        }
+    }
+
+    let pruned: usize = summaries.iter().map(|summary| summary.pruned.len()).sum();
+    Result::Ok(Report::new_with_data(
+        SCOPE.to_string(),
+        format!("pruned {} items of {} feeds", pruned, summaries.len()),
+        summaries,
+    ))
+}
+
+/// Fairing that sweeps the feeds periodically, every retention_sweep_interval seconds
+/// Listing the feeds also publishes their due scheduled items, which renders them again with the
+/// filesystem backend
+/// Setting the interval to 0 disables the sweeper
+pub fn sweeper_fairing() -> AdHoc {
+    AdHoc::on_attach("Retention sweeper", |rocket| {
+        let interval: i64 = rocket
+            .config()
+            .get_int("retention_sweep_interval")
+            .unwrap_or(DEFAULT_SWEEP_INTERVAL);
+        if interval <= 0 {
+            info!("the retention sweeper is disabled");
+            return Result::Ok(rocket);
+        }
+
+        let store: Store = match Store::get_one(&rocket) {
+            Some(value) => value,
+            None => {
+                error!("failed to get the storage backend for the retention sweeper");
+                return Result::Err(rocket);
+            }
+        };
+
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(interval as u64));
+            match sweep(store.clone()) {
+                Ok(report) => info!("{:?}", report),
+                Err(e) => warn!("failed to sweep the feeds: {}", e),
+            }
+        });
+
+        Result::Ok(rocket)
+    })
+}
+
+#[cfg(all(test, feature = "inmemory"))]
+mod test {
+    use super::prune;
+
+    use crate::db::{
+        model::{
+            aux::{ItemState, RetentionAction, RetentionPolicy},
+            Feed, FeedItem,
+        },
+        wrappers::inmemory::MemoryStore,
+        FeedItemWrapper, FeedWrapper,
+    };
+
+    use chrono::{Duration, Utc};
+
+    /// Create a feed that keeps its newest item, along with three items published a day apart
+    fn create_feed(store: &MemoryStore, action: RetentionAction) -> Feed {
+        let mut feed = Feed::new("Title", "Description", "https://example.com").unwrap();
+        feed.retention = Option::Some(RetentionPolicy {
+            max_items: Option::Some(1),
+            max_age_days: Option::None,
+            action,
+        });
+        let feed: Feed = store.clone().create_feed(feed).unwrap();
+
+        for days in 1..4 {
+            let mut feed_item =
+                FeedItem::new("Item", "https://example.com/item", "Description").unwrap();
+            feed_item.published_at = Option::Some(Utc::now() - Duration::days(days));
+            store
+                .clone()
+                .create_feed_item(feed.clone(), feed_item)
+                .unwrap();
+        }
+        store.clone().get_feed(feed.get_uuid().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn prune_delete_test() {
+        let store: MemoryStore = MemoryStore::default();
+        let feed: Feed = create_feed(&store, RetentionAction::Delete);
+
+        let summary = prune(store.clone(), feed.clone()).unwrap();
+        assert_eq!(summary.pruned.len(), 2);
+        assert!(summary.errors.is_empty());
+
+        let items = store
+            .clone()
+            .get_feed_items(feed.clone(), Option::None, true)
+            .unwrap();
+        assert_eq!(items.items.len(), 1);
+        assert_ne!(
+            store
+                .clone()
+                .get_feed_checksum(feed.get_uuid().unwrap())
+                .unwrap(),
+            feed.get_checksum().unwrap()
+        );
+
+        // Nothing falls out of the policy anymore
+        let feed: Feed = store.clone().get_feed(feed.get_uuid().unwrap()).unwrap();
+        assert!(prune(store, feed).unwrap().pruned.is_empty());
+    }
+
+    #[test]
+    fn prune_archive_test() {
+        let store: MemoryStore = MemoryStore::default();
+        let feed: Feed = create_feed(&store, RetentionAction::Archive);
+
+        let summary = prune(store.clone(), feed.clone()).unwrap();
+        assert_eq!(summary.pruned.len(), 2);
+
+        let items = store
+            .get_feed_items(feed, Option::None, true)
+            .unwrap()
+            .items;
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items
+                .iter()
+                .filter(|feed_item| feed_item.get_state() == ItemState::Archived)
+                .count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,556 @@
+use crate::common::{report::Report, DbResult};
+
+use super::{
+    model::{
+        aux::{ItemState, RetentionAction},
+        Feed, FeedItem,
+    },
+    query::{FeedQuery, ItemQuery, Page},
+    FeedItemWrapper, FeedWrapper, ItemsLookup,
+};
+
+#[cfg(feature = "filesystem")]
+use super::wrappers::filesystem::FileStore;
+#[cfg(feature = "inmemory")]
+use super::wrappers::inmemory::MemoryStore;
+#[cfg(feature = "mongo")]
+use super::wrappers::mongo::MongoStore;
+#[cfg(feature = "postgres")]
+use super::wrappers::postgres::PostgresStore;
+#[cfg(feature = "sqlite")]
+use super::wrappers::sqlite::SqliteStore;
+
+#[cfg(any(feature = "mongo", feature = "sqlite", feature = "postgres"))]
+use {
+    crate::{
+        common::errors::{BackendErrorKind, StorageError},
+        create_error,
+    },
+    rocket::Config,
+    rocket_contrib::databases::{
+        database_config,
+        r2d2::{ManageConnection, Pool, PooledConnection},
+        Poolable,
+    },
+    std::fmt::Debug,
+};
+
+use std::sync::Arc;
+
+use log::*;
+use rocket::{
+    fairing::AdHoc,
+    request::{self, FromRequest, Request},
+    Rocket, State,
+};
+use uuid::Uuid;
+
+#[cfg(any(feature = "mongo", feature = "sqlite", feature = "postgres"))]
+const SCOPE: &str = "database/storage";
+
+/// The name of the database, in [global.databases], used by the backends that connect to one
+#[cfg(any(feature = "mongo", feature = "sqlite", feature = "postgres"))]
+const DATABASE_NAME: &str = "feeder";
+
+/// The operations of a storage backend, one for each operation of the wrapper traits
+/// A backend is shared by all the requests, so it gets a connection for each operation instead
+/// of being consumed by it
+pub trait Storage: Send + Sync {
+    fn create_feed(&self, feed: Feed) -> DbResult<Feed>;
+
+    fn get_feed(&self, uuid: Uuid) -> DbResult<Feed>;
+
+    fn get_feeds(&self) -> DbResult<Vec<Feed>>;
+
+    fn list_feeds(&self, query: FeedQuery) -> DbResult<Page<Feed>>;
+
+    fn update_feed(&self, uuid: Uuid, feed: Feed, checksum: Option<String>) -> DbResult<Feed>;
+
+    fn delete_feed(&self, uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>>;
+
+    fn get_feed_checksum(&self, uuid: Uuid) -> DbResult<String>;
+
+    fn create_feed_item(&self, parent_feed: Feed, feed_item: FeedItem) -> DbResult<FeedItem>;
+
+    fn get_feed_item(&self, parent_feed: Feed, uuid: Uuid) -> DbResult<FeedItem>;
+
+    fn get_feed_items(
+        &self,
+        parent_feed: Feed,
+        uuids: Option<Vec<Uuid>>,
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup>;
+
+    fn list_feed_items(&self, parent_feed: Feed, query: ItemQuery) -> DbResult<Page<FeedItem>>;
+
+    fn update_feed_item(
+        &self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        feed_item: FeedItem,
+        checksum: Option<String>,
+    ) -> DbResult<FeedItem>;
+
+    fn delete_feed_item(
+        &self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        checksum: Option<String>,
+    ) -> DbResult<Report<String>>;
+
+    fn set_feed_item_state(
+        &self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        state: ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<FeedItem>;
+
+    fn get_feed_item_checksum(&self, parent_feed: Feed, uuid: Uuid) -> DbResult<String>;
+
+    fn prune_feed_items(
+        &self,
+        parent_feed: Feed,
+        uuids: Vec<Uuid>,
+        action: RetentionAction,
+    ) -> DbResult<Vec<Uuid>>;
+
+    /// Migrate the stored feeds and feed items to the current models
+    fn migrate(&self) -> DbResult<Report<String>>;
+}
+
+/// Implement Storage for a backend, given how it gets a connection that implements the wrapper
+/// traits and how it migrates
+/// e.g. impl_storage!(SqliteStore, |store| &*store.connection()?, |store| migrate(...))
+#[macro_export]
+macro_rules! impl_storage {
+    ($backend: ty, |$storage: pat| $connection: expr, |$migrated: pat| $migrate: expr) => {
+        impl $crate::db::storage::Storage for $backend {
+            fn create_feed(
+                &self,
+                feed: $crate::db::model::Feed,
+            ) -> $crate::common::DbResult<$crate::db::model::Feed> {
+                let $storage = self;
+                $crate::db::FeedWrapper::create_feed($connection, feed)
+            }
+
+            fn get_feed(
+                &self,
+                uuid: uuid::Uuid,
+            ) -> $crate::common::DbResult<$crate::db::model::Feed> {
+                let $storage = self;
+                $crate::db::FeedWrapper::get_feed($connection, uuid)
+            }
+
+            fn get_feeds(&self) -> $crate::common::DbResult<Vec<$crate::db::model::Feed>> {
+                let $storage = self;
+                $crate::db::FeedWrapper::get_feeds($connection)
+            }
+
+            fn list_feeds(
+                &self,
+                query: $crate::db::query::FeedQuery,
+            ) -> $crate::common::DbResult<$crate::db::query::Page<$crate::db::model::Feed>> {
+                let $storage = self;
+                $crate::db::FeedWrapper::list_feeds($connection, query)
+            }
+
+            fn update_feed(
+                &self,
+                uuid: uuid::Uuid,
+                feed: $crate::db::model::Feed,
+                checksum: Option<String>,
+            ) -> $crate::common::DbResult<$crate::db::model::Feed> {
+                let $storage = self;
+                $crate::db::FeedWrapper::update_feed($connection, uuid, feed, checksum)
+            }
+
+            fn delete_feed(
+                &self,
+                uuid: uuid::Uuid,
+                checksum: Option<String>,
+            ) -> $crate::common::DbResult<$crate::common::report::Report<String>> {
+                let $storage = self;
+                $crate::db::FeedWrapper::delete_feed($connection, uuid, checksum)
+            }
+
+            fn get_feed_checksum(&self, uuid: uuid::Uuid) -> $crate::common::DbResult<String> {
+                let $storage = self;
+                $crate::db::FeedWrapper::get_feed_checksum($connection, uuid)
+            }
+
+            fn create_feed_item(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                feed_item: $crate::db::model::FeedItem,
+            ) -> $crate::common::DbResult<$crate::db::model::FeedItem> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::create_feed_item($connection, parent_feed, feed_item)
+            }
+
+            fn get_feed_item(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuid: uuid::Uuid,
+            ) -> $crate::common::DbResult<$crate::db::model::FeedItem> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::get_feed_item($connection, parent_feed, uuid)
+            }
+
+            fn get_feed_items(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuids: Option<Vec<uuid::Uuid>>,
+                include_unpublished: bool,
+            ) -> $crate::common::DbResult<$crate::db::ItemsLookup> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::get_feed_items(
+                    $connection,
+                    parent_feed,
+                    uuids,
+                    include_unpublished,
+                )
+            }
+
+            fn list_feed_items(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                query: $crate::db::query::ItemQuery,
+            ) -> $crate::common::DbResult<$crate::db::query::Page<$crate::db::model::FeedItem>>
+            {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::list_feed_items($connection, parent_feed, query)
+            }
+
+            fn update_feed_item(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuid: uuid::Uuid,
+                feed_item: $crate::db::model::FeedItem,
+                checksum: Option<String>,
+            ) -> $crate::common::DbResult<$crate::db::model::FeedItem> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::update_feed_item(
+                    $connection,
+                    parent_feed,
+                    uuid,
+                    feed_item,
+                    checksum,
+                )
+            }
+
+            fn delete_feed_item(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuid: uuid::Uuid,
+                checksum: Option<String>,
+            ) -> $crate::common::DbResult<$crate::common::report::Report<String>> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::delete_feed_item(
+                    $connection,
+                    parent_feed,
+                    uuid,
+                    checksum,
+                )
+            }
+
+            fn set_feed_item_state(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuid: uuid::Uuid,
+                state: $crate::db::model::aux::ItemState,
+                checksum: Option<String>,
+            ) -> $crate::common::DbResult<$crate::db::model::FeedItem> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::set_feed_item_state(
+                    $connection,
+                    parent_feed,
+                    uuid,
+                    state,
+                    checksum,
+                )
+            }
+
+            fn get_feed_item_checksum(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuid: uuid::Uuid,
+            ) -> $crate::common::DbResult<String> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::get_feed_item_checksum($connection, parent_feed, uuid)
+            }
+
+            fn prune_feed_items(
+                &self,
+                parent_feed: $crate::db::model::Feed,
+                uuids: Vec<uuid::Uuid>,
+                action: $crate::db::model::aux::RetentionAction,
+            ) -> $crate::common::DbResult<Vec<uuid::Uuid>> {
+                let $storage = self;
+                $crate::db::FeedItemWrapper::prune_feed_items(
+                    $connection,
+                    parent_feed,
+                    uuids,
+                    action,
+                )
+            }
+
+            fn migrate(&self) -> $crate::common::DbResult<$crate::common::report::Report<String>> {
+                let $migrated = self;
+                $migrate
+            }
+        }
+    };
+}
+
+/// Handle to the storage backend chosen at startup, shared by the routes
+/// It implements the wrapper traits, so it is used like a connection to any of the backends
+#[derive(Clone)]
+pub struct Store(Arc<dyn Storage>);
+
+impl Store {
+    /// Create a handle to a storage backend
+    pub fn new<S>(storage: S) -> Self
+    where
+        S: Storage + 'static,
+    {
+        Store(Arc::new(storage))
+    }
+
+    /// Fairing that creates the storage backend selected by the storage setting
+    /// Without the setting, the backend is the first of mongo, postgres, sqlite, filesystem and
+    /// inmemory that feeder was built with
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Storage", |rocket| {
+            let backend: String = rocket
+                .config()
+                .get_string("storage")
+                .unwrap_or_else(|_| default_backend().to_string());
+
+            let store: Option<Store> = match backend.as_str() {
+                #[cfg(feature = "mongo")]
+                "mongo" => MongoStore::from_config(rocket.config()).map(Store::new),
+                #[cfg(feature = "postgres")]
+                "postgres" => PostgresStore::from_config(rocket.config()).map(Store::new),
+                #[cfg(feature = "sqlite")]
+                "sqlite" => SqliteStore::from_config(rocket.config()).map(Store::new),
+                #[cfg(feature = "filesystem")]
+                "filesystem" => FileStore::from_config(rocket.config()).map(Store::new),
+                #[cfg(feature = "inmemory")]
+                "inmemory" => Option::Some(Store::new(MemoryStore::default())),
+                _ => {
+                    error!("feeder was not built with the {} storage backend", backend);
+                    Option::None
+                }
+            };
+
+            match store {
+                Some(value) => {
+                    info!("storing the feeds with the {} backend", backend);
+                    Result::Ok(rocket.manage(value))
+                }
+                None => Result::Err(rocket),
+            }
+        })
+    }
+
+    /// Get a handle to the storage backend outside of a request
+    pub fn get_one(rocket: &Rocket) -> Option<Self> {
+        rocket.state::<Store>().cloned()
+    }
+
+    /// Migrate the stored feeds and feed items to the current models
+    pub fn migrate(&self) -> DbResult<Report<String>> {
+        self.0.migrate()
+    }
+}
+
+/// Implementation of the FeederWrapper for the storage handle
+impl FeedWrapper for Store {
+    fn create_feed(self, feed: Feed) -> DbResult<Feed> {
+        self.0.create_feed(feed)
+    }
+
+    fn get_feed(self, uuid: Uuid) -> DbResult<Feed> {
+        self.0.get_feed(uuid)
+    }
+
+    fn get_feeds(self) -> DbResult<Vec<Feed>> {
+        self.0.get_feeds()
+    }
+
+    fn list_feeds(self, query: FeedQuery) -> DbResult<Page<Feed>> {
+        self.0.list_feeds(query)
+    }
+
+    fn update_feed(self, uuid: Uuid, feed: Feed, checksum: Option<String>) -> DbResult<Feed> {
+        self.0.update_feed(uuid, feed, checksum)
+    }
+
+    fn delete_feed(self, _uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>> {
+        self.0.delete_feed(_uuid, checksum)
+    }
+
+    fn get_feed_checksum(self, _uuid: Uuid) -> DbResult<String> {
+        self.0.get_feed_checksum(_uuid)
+    }
+}
+
+/// Implementation of FeedItemWrapper for the storage handle
+impl FeedItemWrapper for Store {
+    fn create_feed_item(self, parent_feed: Feed, feed_item: FeedItem) -> DbResult<FeedItem> {
+        self.0.create_feed_item(parent_feed, feed_item)
+    }
+
+    fn get_feed_item(self, parent_feed: Feed, uuid: Uuid) -> DbResult<FeedItem> {
+        self.0.get_feed_item(parent_feed, uuid)
+    }
+
+    fn get_feed_items(
+        self,
+        parent_feed: Feed,
+        uuids: Option<Vec<Uuid>>,
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup> {
+        self.0
+            .get_feed_items(parent_feed, uuids, include_unpublished)
+    }
+
+    fn list_feed_items(self, parent_feed: Feed, query: ItemQuery) -> DbResult<Page<FeedItem>> {
+        self.0.list_feed_items(parent_feed, query)
+    }
+
+    fn update_feed_item(
+        self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        feed_item: FeedItem,
+        checksum: Option<String>,
+    ) -> DbResult<FeedItem> {
+        self.0
+            .update_feed_item(parent_feed, uuid, feed_item, checksum)
+    }
+
+    fn delete_feed_item(
+        self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        checksum: Option<String>,
+    ) -> DbResult<Report<String>> {
+        self.0.delete_feed_item(parent_feed, uuid, checksum)
+    }
+
+    fn set_feed_item_state(
+        self,
+        parent_feed: Feed,
+        uuid: Uuid,
+        state: ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<FeedItem> {
+        self.0
+            .set_feed_item_state(parent_feed, uuid, state, checksum)
+    }
+
+    fn get_feed_item_checksum(self, parent_feed: Feed, uuid: Uuid) -> DbResult<String> {
+        self.0.get_feed_item_checksum(parent_feed, uuid)
+    }
+
+    fn prune_feed_items(
+        self,
+        parent_feed: Feed,
+        uuids: Vec<Uuid>,
+        action: RetentionAction,
+    ) -> DbResult<Vec<Uuid>> {
+        self.0.prune_feed_items(parent_feed, uuids, action)
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Store {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        request
+            .guard::<State<Store>>()
+            .map(|store| store.inner().clone())
+    }
+}
+
+/// Get the storage backend used when the storage setting is missing
+fn default_backend() -> &'static str {
+    if cfg!(feature = "mongo") {
+        "mongo"
+    } else if cfg!(feature = "postgres") {
+        "postgres"
+    } else if cfg!(feature = "sqlite") {
+        "sqlite"
+    } else if cfg!(feature = "filesystem") {
+        "filesystem"
+    } else {
+        "inmemory"
+    }
+}
+
+/// Create the pool of connections to the feeder database of [global.databases]
+#[cfg(any(feature = "mongo", feature = "sqlite", feature = "postgres"))]
+pub fn pool<P>(config: &Config) -> Option<Pool<P::Manager>>
+where
+    P: Poolable,
+    P::Error: Debug,
+{
+    let database: rocket_contrib::databases::DatabaseConfig =
+        match database_config(DATABASE_NAME, config) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("the {} database is not configured: {:?}", DATABASE_NAME, e);
+                return Option::None;
+            }
+        };
+
+    match P::pool(database) {
+        Ok(value) => Option::Some(value),
+        Err(e) => {
+            error!(
+                "failed to connect to the {} database: {:?}",
+                DATABASE_NAME, e
+            );
+            Option::None
+        }
+    }
+}
+
+/// Get a connection from a pool
+#[cfg(any(feature = "mongo", feature = "sqlite", feature = "postgres"))]
+pub fn connection<M>(pool: &Pool<M>) -> DbResult<PooledConnection<M>>
+where
+    M: ManageConnection,
+{
+    pool.get().map_err(|e| {
+        warn!("failed to get a database connection: {:?}", e);
+        create_error!(SCOPE, StorageError::NoConnection)
+            .caused_by(BackendErrorKind::ConnectionRefused, &e)
+    })
+}
+
+/// Fairing that migrates the storage backend when the router starts
+pub fn migrations_fairing() -> AdHoc {
+    AdHoc::on_attach("Database migrations", |rocket| {
+        let store: Store = match Store::get_one(&rocket) {
+            Some(value) => value,
+            None => {
+                error!("failed to get the storage backend for the migrations");
+                return Result::Err(rocket);
+            }
+        };
+
+        match store.migrate() {
+            Ok(report) => {
+                info!("{:?}", report);
+                Result::Ok(rocket)
+            }
+            Err(e) => {
+                error!("failed to migrate the database: {}", e);
+                Result::Err(rocket)
+            }
+        }
+    })
+}
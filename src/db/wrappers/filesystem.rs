@@ -0,0 +1,1016 @@
+use crate::{
+    common::{
+        errors::{BackendErrorKind, Error, FeedDbError, FeedItemDbError},
+        report::Report,
+        DbResult,
+    },
+    create_error, impl_storage,
+    syndication::Format,
+};
+
+use crate::db::{
+    model::{
+        aux::{ItemState, RetentionAction},
+        ItemsVec,
+    },
+    query::{FeedQuery, FeedSort, ItemOrder, ItemQuery, Page, Pagination},
+    *,
+};
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use chrono::{DateTime, Utc};
+use log::*;
+use rocket::Config;
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+const SCOPE: &str = "database/filesystem";
+
+/// The default directory the feeds are stored in
+const DEFAULT_STORAGE_DIR: &str = "~/rss";
+
+/// The file that holds a feed, along with the order of its items
+const MANIFEST_FILE: &str = "manifest.json";
+/// The directory that holds the items of a feed, one file per item
+const ITEMS_DIR: &str = "items";
+/// The rss rendering of a feed, kept up to date with its published items
+const RENDERING_FILE: &str = "feed.xml";
+
+/// The manifest of a feed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Manifest {
+    feed: model::Feed,
+    /// The uuids of the items of the feed, in the order they were created in
+    items: Vec<Uuid>,
+}
+
+/// A store that keeps each feed in a directory of the storage directory:
+/// <uuid>/manifest.json, <uuid>/items/<item uuid>.json and <uuid>/feed.xml
+/// Files are replaced atomically, so the storage directory can be versioned and served as is
+/// Clones of the store share the same lock, which serializes the changes made by this process
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    root: PathBuf,
+    lock: Arc<RwLock<()>>,
+}
+
+impl FileStore {
+    /// Create a store in a directory, which is created if it does not exist
+    pub fn new(root: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&root)?;
+        Result::Ok(FileStore {
+            root,
+            lock: Arc::new(RwLock::new(())),
+        })
+    }
+
+    /// Create a store in the directory configured by rss_storage_dir
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let value: &str = config
+            .get_str("rss_storage_dir")
+            .unwrap_or(DEFAULT_STORAGE_DIR);
+        let root: PathBuf = match storage_dir(value) {
+            Some(path) => path,
+            None => {
+                error!(
+                    "cannot expand the storage directory {:?}, only ~ and ~/ are expanded, to HOME",
+                    value
+                );
+                return Option::None;
+            }
+        };
+
+        match FileStore::new(root.clone()) {
+            Ok(store) => {
+                info!("the feeds are stored in {:?}", root);
+                Option::Some(store)
+            }
+            Err(e) => {
+                error!("failed to create the storage directory {:?}: {:?}", root, e);
+                Option::None
+            }
+        }
+    }
+
+    /// Lock the store for reading
+    /// The files are only replaced atomically, so they are used even if a thread panicked while
+    /// holding the lock
+    fn read(&self) -> RwLockReadGuard<()> {
+        self.lock.read().unwrap_or_else(|e| {
+            warn!("the file store was poisoned");
+            e.into_inner()
+        })
+    }
+
+    /// Lock the store for writing
+    fn write(&self) -> RwLockWriteGuard<()> {
+        self.lock.write().unwrap_or_else(|e| {
+            warn!("the file store was poisoned");
+            e.into_inner()
+        })
+    }
+
+    /// Get the directory of a feed
+    fn feed_dir(&self, uuid: Uuid) -> PathBuf {
+        self.root.join(format!("{}", uuid))
+    }
+
+    /// Get the file of a feed item
+    fn item_path(&self, feed_uuid: Uuid, uuid: Uuid) -> PathBuf {
+        self.feed_dir(feed_uuid)
+            .join(ITEMS_DIR)
+            .join(format!("{}.json", uuid))
+    }
+
+    /// Read the manifest of a feed, if the feed exists
+    fn read_manifest(&self, uuid: Uuid) -> DbResult<Option<Manifest>> {
+        read_json(
+            &self.feed_dir(uuid).join(MANIFEST_FILE),
+            create_error!(SCOPE, FeedDbError::FailedToGetFeeds),
+        )
+    }
+
+    /// Read the manifests of all the feeds, in the order the feeds were created in
+    /// Entries that are not feeds, such as feeds that are being created or deleted, are skipped
+    fn read_manifests(&self) -> DbResult<Vec<Manifest>> {
+        let entries: fs::ReadDir = match fs::read_dir(&self.root) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("failed to list the feeds: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToGetFeeds),
+                    &e,
+                ));
+            }
+        };
+
+        let mut manifests: Vec<Manifest> = Vec::new();
+        for entry in entries {
+            let uuid: Option<Uuid> = entry
+                .ok()
+                .and_then(|value| value.file_name().into_string().ok())
+                .and_then(|name| Uuid::from_str(&name).ok());
+            if let Some(value) = uuid {
+                if let Some(manifest) = self.read_manifest(value)? {
+                    manifests.push(manifest);
+                }
+            }
+        }
+
+        manifests.sort_by(|a, b| {
+            (a.feed.get_created_at(), a.feed.get_uuid())
+                .cmp(&(b.feed.get_created_at(), b.feed.get_uuid()))
+        });
+        Result::Ok(manifests)
+    }
+
+    /// Read a feed item, if the feed has it
+    fn read_item(&self, feed_uuid: Uuid, uuid: Uuid) -> DbResult<Option<model::FeedItem>> {
+        read_json(
+            &self.item_path(feed_uuid, uuid),
+            create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+        )
+    }
+
+    /// Read the items of a feed, in the order they were created in
+    fn read_items(&self, manifest: &Manifest) -> DbResult<Vec<model::FeedItem>> {
+        let feed_uuid: Uuid = manifest.feed.get_uuid().unwrap();
+        let mut items_vec: Vec<model::FeedItem> = Vec::new();
+        for uuid in &manifest.items {
+            match self.read_item(feed_uuid, *uuid)? {
+                Some(feed_item) => items_vec.push(feed_item),
+                None => warn!("the feed item {} listed by the manifest has no file", uuid),
+            }
+        }
+        Result::Ok(items_vec)
+    }
+
+    /// Save a feed item
+    fn write_item(&self, feed_item: &model::FeedItem, error: Error) -> DbResult<()> {
+        write_json(
+            &self.item_path(
+                feed_item.get_feed_uuid().unwrap(),
+                feed_item.get_uuid().unwrap(),
+            ),
+            feed_item,
+            error,
+        )
+    }
+
+    /// Save the manifest of a feed, along with the rendering of its published items
+    fn save(&self, manifest: &Manifest, error: Error) -> DbResult<()> {
+        let feed_dir: PathBuf = self.feed_dir(manifest.feed.get_uuid().unwrap());
+        write_json(&feed_dir.join(MANIFEST_FILE), manifest, error.clone())?;
+
+        let now: DateTime<Utc> = Utc::now();
+        let mut feed: model::Feed = manifest.feed.clone();
+        feed.items = Option::Some(ItemsVec::Full(
+            self.read_items(manifest)?
+                .into_iter()
+                .filter(|feed_item| is_published(feed_item, false, now))
+                .collect(),
+        ));
+        write_atomically(
+            &feed_dir.join(RENDERING_FILE),
+            Format::Rss.render(&feed).as_bytes(),
+        )
+        .map_err(|e| {
+            warn!("failed to save the rendering of the feed: {:?}", e);
+            caused_by(error, &e)
+        })
+    }
+
+    /// Recompute the checksum of a feed after it or its items changed, then save it
+    fn refresh(&self, manifest: &mut Manifest, error: Error) -> DbResult<()> {
+        let now: DateTime<Utc> = Utc::now();
+        manifest.feed.set_updated_at(now);
+        manifest.feed.items = Option::Some(ItemsVec::Full(
+            self.read_items(manifest)?
+                .into_iter()
+                .filter(|feed_item| is_published(feed_item, false, now))
+                .collect(),
+        ));
+        let checksum_error: Option<Error> = manifest.feed.compute_checksum();
+        manifest.feed.items = Option::None;
+        if let Some(e) = checksum_error {
+            return Result::Err(e);
+        }
+
+        self.save(manifest, error)
+    }
+
+    /// Read the manifest of the parent feed of an item, which must still exist
+    fn parent_manifest(&self, parent_feed: &model::Feed) -> DbResult<Manifest> {
+        match self.read_manifest(parent_feed.get_uuid().unwrap())? {
+            Some(value) => Result::Ok(value),
+            None => {
+                warn!("the parent feed does not exist anymore");
+                Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound))
+            }
+        }
+    }
+
+    /// Read a stored feed item, checking its checksum against the expected one
+    fn stored_item(
+        &self,
+        parent_feed: &model::Feed,
+        uuid: Uuid,
+        checksum: &Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        let stored: model::FeedItem = match self.read_item(parent_feed.get_uuid().unwrap(), uuid)? {
+            Some(value) => value,
+            None => {
+                warn!("parent feed has no matching feed item");
+                return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
+            }
+        };
+        // Compare and set: nothing is changed if the item changed in the meantime
+        if !checksum_matches(stored.get_checksum(), checksum) {
+            warn!("the checksum of the feed item does not match the expected one");
+            return Result::Err(create_error!(SCOPE, FeedItemDbError::ChecksumMismatch));
+        }
+        Result::Ok(stored)
+    }
+}
+
+/// Implementation of the FeederWrapper for the file store
+impl FeedWrapper for FileStore {
+    fn create_feed(self, feed: model::Feed) -> DbResult<model::Feed> {
+        let mut created_feed: model::Feed = model::Feed::new_from_model(feed)?;
+        created_feed.set_created_at(Utc::now());
+
+        let _lock = self.write();
+        let items_dir: PathBuf = self
+            .feed_dir(created_feed.get_uuid().unwrap())
+            .join(ITEMS_DIR);
+        if let Err(e) = fs::create_dir_all(&items_dir) {
+            warn!("error while creating the directory of the feed: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToSaveFeed),
+                &e,
+            ));
+        }
+
+        // The feed exists once its manifest is saved
+        let manifest: Manifest = Manifest {
+            feed: created_feed.clone(),
+            items: Vec::new(),
+        };
+        self.save(
+            &manifest,
+            create_error!(SCOPE, FeedDbError::FailedToSaveFeed),
+        )?;
+
+        Result::Ok(created_feed)
+    }
+
+    fn get_feed(self, uuid: Uuid) -> DbResult<model::Feed> {
+        let manifest: Option<Manifest> = {
+            let _lock = self.read();
+            self.read_manifest(uuid)?
+        };
+
+        match manifest {
+            Some(value) => publish_due_items(&self, value),
+            None => {
+                warn!("the store has no such feed");
+                Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound))
+            }
+        }
+    }
+
+    fn get_feeds(self) -> DbResult<Vec<model::Feed>> {
+        let manifests: Vec<Manifest> = {
+            let _lock = self.read();
+            self.read_manifests()?
+        };
+
+        // The renderings are served as they are, so the feeds whose scheduled items went live are
+        // rendered again here as well, which the retention sweeper does periodically
+        manifests
+            .into_iter()
+            .map(|manifest| publish_due_items(&self, manifest))
+            .collect()
+    }
+
+    fn list_feeds(self, query: FeedQuery) -> DbResult<Page<model::Feed>> {
+        let mut feeds: Vec<model::Feed> = self
+            .clone()
+            .get_feeds()?
+            .into_iter()
+            .filter(|feed| query.category.is_none() || feed.category == query.category)
+            .filter(|feed| query.language.is_none() || feed.language == query.language)
+            .collect();
+
+        // The feeds are read in the order they were created in, the sort is stable so that order
+        // breaks the ties when sorting by title
+        match query.sort {
+            FeedSort::TitleAscending => feeds.sort_by(|a, b| a.title.cmp(&b.title)),
+            FeedSort::TitleDescending => {
+                feeds.reverse();
+                feeds.sort_by(|a, b| b.title.cmp(&a.title));
+            }
+            FeedSort::CreatedAscending => {}
+            FeedSort::CreatedDescending => feeds.reverse(),
+        }
+
+        // Take one more feed than requested in order to know if there is a next page
+        Result::Ok(Page::from_results(
+            paginate(feeds, &query.pagination),
+            query.pagination.offset,
+            query.pagination.limit,
+        ))
+    }
+
+    fn update_feed(
+        self,
+        uuid: Uuid,
+        mut feed: model::Feed,
+        checksum: Option<String>,
+    ) -> DbResult<model::Feed> {
+        {
+            let _lock = self.write();
+            let mut manifest: Manifest = match self.read_manifest(uuid)? {
+                Some(value) => value,
+                None => {
+                    warn!("the store has no such feed");
+                    return Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound));
+                }
+            };
+            // Compare and set: nothing is updated if the feed changed in the meantime
+            if !checksum_matches(manifest.feed.get_checksum(), &checksum) {
+                warn!("the checksum of the feed does not match the expected one");
+                return Result::Err(create_error!(SCOPE, FeedDbError::ChecksumMismatch));
+            }
+
+            // Items are stored separately and listed by the manifest
+            feed.items = Option::None;
+            feed.keep_managed_fields(&manifest.feed);
+            manifest.feed = feed;
+            self.refresh(
+                &mut manifest,
+                create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+            )?;
+        }
+
+        self.get_feed(uuid)
+    }
+
+    fn delete_feed(self, _uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>> {
+        let _lock = self.write();
+        let manifest: Manifest = match self.read_manifest(_uuid)? {
+            Some(value) => value,
+            None => {
+                warn!("the store has no such feed");
+                return Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound));
+            }
+        };
+        // Compare and delete: nothing is deleted if the feed changed in the meantime
+        if !checksum_matches(manifest.feed.get_checksum(), &checksum) {
+            warn!("the checksum of the feed does not match the expected one");
+            return Result::Err(create_error!(SCOPE, FeedDbError::ChecksumMismatch));
+        }
+
+        // The directory is moved out of the way first, so the feed and its items disappear at once
+        let deleted_dir: PathBuf = self.root.join(format!(".deleted-{}", _uuid));
+        if let Err(e) = fs::rename(self.feed_dir(_uuid), &deleted_dir)
+            .and_then(|_| fs::remove_dir_all(&deleted_dir))
+        {
+            warn!("failed to delete the feed: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToDeleteFeed),
+                &e,
+            ));
+        }
+
+        Result::Ok(Report::new(SCOPE.to_string(), "deleted feed".to_string()))
+    }
+
+    fn get_feed_checksum(self, _uuid: Uuid) -> DbResult<String> {
+        let feed: model::Feed = self.get_feed(_uuid)?;
+        if let Some(value) = feed.get_checksum() {
+            return Result::Ok(value);
+        }
+        warn!("the feed has no checksum");
+        Result::Err(create_error!(SCOPE, FeedDbError::FeedHasNoChecksum))
+    }
+}
+
+/// Implementation of FeedItemWrapper for the file store
+impl FeedItemWrapper for FileStore {
+    fn create_feed_item(
+        self,
+        parent_feed: model::Feed,
+        feed_item: model::FeedItem,
+    ) -> DbResult<model::FeedItem> {
+        let mut created_feed_item = model::FeedItem::new_from_model(feed_item)?;
+        created_feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        created_feed_item.set_created_at(Utc::now());
+
+        let _lock = self.write();
+        let mut manifest: Manifest = self.parent_manifest(&parent_feed)?;
+
+        // The item is saved before the manifest lists it, so the manifest never lists an item
+        // that has no file
+        self.write_item(
+            &created_feed_item,
+            create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+        )?;
+        manifest.items.push(created_feed_item.get_uuid().unwrap());
+        self.refresh(
+            &mut manifest,
+            create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+        )?;
+
+        Result::Ok(created_feed_item)
+    }
+
+    fn get_feed_item(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<model::FeedItem> {
+        let _lock = self.read();
+        match self.read_item(parent_feed.get_uuid().unwrap(), uuid)? {
+            Some(feed_item) => Result::Ok(feed_item),
+            None => {
+                warn!("parent feed has no matching feed item");
+                Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound))
+            }
+        }
+    }
+
+    fn get_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Option<Vec<Uuid>>,
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup> {
+        // If no uuids are passed to the function, search for all feed items in this feed
+        // Otherwise, search only for the specified ones
+        // Items are read in the order they were created in
+        let now: DateTime<Utc> = Utc::now();
+        let items_vec: Vec<model::FeedItem> = {
+            let _lock = self.read();
+            let manifest: Manifest = self.parent_manifest(&parent_feed)?;
+            self.read_items(&manifest)?
+                .into_iter()
+                .filter(|feed_item| match &uuids {
+                    Some(uuid_vec) => feed_item
+                        .get_uuid()
+                        .map_or(false, |value| uuid_vec.contains(&value)),
+                    None => true,
+                })
+                .filter(|feed_item| include_unpublished || is_published(feed_item, false, now))
+                .collect()
+        };
+
+        // The requested uuids that were not found are missing
+        let mut missing: Vec<Uuid> = Vec::new();
+        if let Some(uuid_vec) = uuids {
+            for item_uuid in uuid_vec {
+                let found: bool = items_vec
+                    .iter()
+                    .any(|feed_item| feed_item.get_uuid() == Option::Some(item_uuid));
+                if !found && !missing.contains(&item_uuid) {
+                    missing.push(item_uuid);
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            warn!("the store has no feed items for {:?}", missing);
+        }
+
+        Result::Ok(ItemsLookup {
+            items: items_vec,
+            missing,
+        })
+    }
+
+    fn list_feed_items(
+        self,
+        parent_feed: model::Feed,
+        query: ItemQuery,
+    ) -> DbResult<Page<model::FeedItem>> {
+        let now: DateTime<Utc> = Utc::now();
+        let mut items_vec: Vec<model::FeedItem> = {
+            let _lock = self.read();
+            let manifest: Manifest = self.parent_manifest(&parent_feed)?;
+            self.read_items(&manifest)?
+                .into_iter()
+                .filter(|feed_item| match query.state {
+                    ItemState::Published => is_published(feed_item, query.include_scheduled, now),
+                    state => feed_item.get_state() == state,
+                })
+                .collect()
+        };
+
+        // Items are ordered by their publication time and then by uuid, items without a
+        // publication time come first in chronological order
+        items_vec.sort_by_key(|feed_item| (feed_item.published_at, feed_item.get_uuid()));
+        if query.order == ItemOrder::NewestFirst {
+            items_vec.reverse();
+        }
+
+        // The page starts right after the last item of the previous page
+        if let Some(cursor) = query.pagination.after {
+            items_vec.retain(|feed_item| cursor.precedes(feed_item, query.order));
+        }
+
+        // Take one more item than requested in order to know if there is a next page
+        items_vec.truncate(query.pagination.limit as usize + 1);
+        Result::Ok(Page::from_items(items_vec, query.pagination.limit))
+    }
+
+    fn update_feed_item(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        mut feed_item: model::FeedItem,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        // The item must stay in its feed
+        feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        feed_item.set_updated_at(Utc::now());
+
+        let _lock = self.write();
+        let mut manifest: Manifest = self.parent_manifest(&parent_feed)?;
+        let stored: model::FeedItem = self.stored_item(&parent_feed, uuid, &checksum)?;
+
+        // The fields managed by feeder are not updated, the checksum covers them
+        feed_item.keep_managed_fields(&stored);
+        if let Some(e) = feed_item.compute_checksum() {
+            warn!("failed to compute the checksum for the feed item");
+            return Result::Err(e);
+        }
+        self.write_item(
+            &feed_item,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+        self.refresh(
+            &mut manifest,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+
+        Result::Ok(feed_item)
+    }
+
+    /// Delete a feed item
+    fn delete_feed_item(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        checksum: Option<String>,
+    ) -> DbResult<Report<String>> {
+        let _lock = self.write();
+        let mut manifest: Manifest = self.parent_manifest(&parent_feed)?;
+        self.stored_item(&parent_feed, uuid, &checksum)?;
+
+        // The manifest stops listing the item before its file is removed
+        manifest.items.retain(|item_uuid| *item_uuid != uuid);
+        self.refresh(
+            &mut manifest,
+            create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+        )?;
+        if let Err(e) = fs::remove_file(self.item_path(parent_feed.get_uuid().unwrap(), uuid)) {
+            warn!("failed to delete the file of the feed item: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                &e,
+            ));
+        }
+
+        Result::Ok(Report::new(
+            SCOPE.to_string(),
+            "deleted feed item".to_string(),
+        ))
+    }
+
+    /// Move a feed item to another stage of its lifecycle
+    fn set_feed_item_state(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        state: ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        let _lock = self.write();
+        let mut manifest: Manifest = self.parent_manifest(&parent_feed)?;
+        let stored: model::FeedItem = self.stored_item(&parent_feed, uuid, &Option::None)?;
+
+        let mut feed_item: model::FeedItem = stored.clone();
+        if let Some(e) = feed_item.transition(state) {
+            return Result::Err(e);
+        }
+        feed_item.set_updated_at(Utc::now());
+
+        // Compare and set: nothing is moved if the item changed in the meantime
+        if !checksum_matches(stored.get_checksum(), &checksum) {
+            warn!("the checksum of the feed item does not match the expected one");
+            return Result::Err(create_error!(SCOPE, FeedItemDbError::ChecksumMismatch));
+        }
+
+        self.write_item(
+            &feed_item,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+        self.refresh(
+            &mut manifest,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+
+        Result::Ok(feed_item)
+    }
+
+    /// Get the checksum of a feed item
+    fn get_feed_item_checksum(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<String> {
+        let feed_item: model::FeedItem;
+
+        // If the feed does not have such item, error
+        match self.get_feed_item(parent_feed, uuid) {
+            Ok(value) => feed_item = value,
+            Err(e) => {
+                warn!("parent feed does not have such item: {:?}", e);
+                return Result::Err(e);
+            }
+        }
+
+        if let Some(value) = feed_item.get_checksum() {
+            return Result::Ok(value);
+        }
+        Result::Err(create_error!(SCOPE, FeedItemDbError::NoChecksum))
+    }
+
+    /// Delete or archive some published feed items at once
+    fn prune_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Vec<Uuid>,
+        action: RetentionAction,
+    ) -> DbResult<Vec<Uuid>> {
+        let _lock = self.write();
+        let mut manifest: Manifest = self.parent_manifest(&parent_feed)?;
+        let mut pruned_items: Vec<model::FeedItem> = self
+            .read_items(&manifest)?
+            .into_iter()
+            .filter(|feed_item| {
+                feed_item.get_state() == ItemState::Published
+                    && feed_item
+                        .get_uuid()
+                        .map_or(false, |value| uuids.contains(&value))
+            })
+            .collect();
+        let pruned: Vec<Uuid> = pruned_items
+            .iter()
+            .filter_map(|feed_item| feed_item.get_uuid())
+            .collect();
+        if pruned.is_empty() {
+            return Result::Ok(pruned);
+        }
+
+        match action {
+            RetentionAction::Delete => {
+                // The manifest stops listing the items before their files are removed
+                manifest
+                    .items
+                    .retain(|item_uuid| !pruned.contains(item_uuid));
+                self.refresh(
+                    &mut manifest,
+                    create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                )?;
+                for item_uuid in &pruned {
+                    let path: PathBuf = self.item_path(parent_feed.get_uuid().unwrap(), *item_uuid);
+                    if let Err(e) = fs::remove_file(path) {
+                        warn!("failed to delete the file of the feed item: {:?}", e);
+                        return Result::Err(caused_by(
+                            create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                            &e,
+                        ));
+                    }
+                }
+            }
+            RetentionAction::Archive => {
+                let now: DateTime<Utc> = Utc::now();
+                for feed_item in pruned_items.iter_mut() {
+                    // Published items can always be archived
+                    if let Some(e) = feed_item.transition(ItemState::Archived) {
+                        return Result::Err(e);
+                    }
+                    feed_item.set_updated_at(now);
+                    self.write_item(
+                        feed_item,
+                        create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+                    )?;
+                }
+                self.refresh(
+                    &mut manifest,
+                    create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+                )?;
+            }
+        }
+
+        Result::Ok(pruned)
+    }
+}
+
+/// Check the stored checksum of a model against the expected one, if there is one
+fn checksum_matches(stored: Option<String>, expected: &Option<String>) -> bool {
+    expected.is_none() || stored == *expected
+}
+
+/// Check whether a feed item is published
+/// Scheduled items are published once their publication time passed
+fn is_published(feed_item: &model::FeedItem, include_scheduled: bool, now: DateTime<Utc>) -> bool {
+    feed_item.get_state() == ItemState::Published
+        && (include_scheduled || feed_item.published_at.map_or(true, |value| value <= now))
+}
+
+/// Take the results of a page, along with one more result if there is a next page
+fn paginate<T>(results: Vec<T>, pagination: &Pagination) -> Vec<T> {
+    results
+        .into_iter()
+        .skip(pagination.offset as usize)
+        .take(pagination.limit as usize + 1)
+        .collect()
+}
+
+/// Refresh the checksum and the rendering of a feed if some of its scheduled items went live
+/// since it was updated
+/// Scheduled items are not covered by the checksum of the feed, so it changes the first time the
+/// feed is read after one of them is published
+fn publish_due_items(store: &FileStore, manifest: Manifest) -> DbResult<model::Feed> {
+    let now: DateTime<Utc> = Utc::now();
+    let updated_at: Option<DateTime<Utc>> = manifest.feed.get_updated_at();
+
+    let due: bool = {
+        let _lock = store.read();
+        store
+            .read_items(&manifest)?
+            .iter()
+            .filter_map(|feed_item| feed_item.published_at)
+            .any(|published_at| Option::Some(published_at) > updated_at && published_at <= now)
+    };
+    if !due {
+        return Result::Ok(manifest.feed);
+    }
+
+    info!(
+        "publishing the scheduled items of feed {:?}",
+        manifest.feed.get_uuid()
+    );
+    let _lock = store.write();
+    // The feed is read again, it could have changed since it was read without the lock
+    match store.read_manifest(manifest.feed.get_uuid().unwrap())? {
+        Some(mut value) => {
+            store.refresh(
+                &mut value,
+                create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+            )?;
+            Result::Ok(value.feed)
+        }
+        None => {
+            warn!("the store has no such feed");
+            Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound))
+        }
+    }
+}
+
+/// Read a json file, if it exists
+fn read_json<T>(path: &Path, error: Error) -> DbResult<Option<T>>
+where
+    T: DeserializeOwned,
+{
+    let contents: String = match fs::read_to_string(path) {
+        Ok(value) => value,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Result::Ok(Option::None),
+        Err(e) => {
+            warn!("failed to read {:?}: {:?}", path, e);
+            return Result::Err(caused_by(error, &e));
+        }
+    };
+
+    serde_json::from_str(&contents)
+        .map(Option::Some)
+        .map_err(|e| {
+            warn!("failed to decode {:?}: {:?}", path, e);
+            error.caused_by(BackendErrorKind::Other, &e)
+        })
+}
+
+/// Write a json file atomically
+/// The json is pretty printed so that the changes to the files are easy to review
+fn write_json<T>(path: &Path, model: &T, error: Error) -> DbResult<()>
+where
+    T: Serialize,
+{
+    let contents: String = match serde_json::to_string_pretty(model) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("failed to encode the model into json: {:?}", e);
+            return Result::Err(error.caused_by(BackendErrorKind::Other, &e));
+        }
+    };
+
+    write_atomically(path, contents.as_bytes()).map_err(|e| {
+        warn!("failed to write {:?}: {:?}", path, e);
+        caused_by(error, &e)
+    })
+}
+
+/// Write a file atomically: the contents are written to a hidden file next to it, which then
+/// replaces it, so readers never see a partially written file
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name: String = path
+        .file_name()
+        .map(|value| value.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temporary: PathBuf = path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+
+    let result: io::Result<()> = File::create(&temporary)
+        .and_then(|mut file| file.write_all(contents).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temporary, path));
+    if result.is_err() {
+        // The temporary file may not exist, depending on where the write failed
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+/// Attach an io error to a feeder error, along with the kind of failure it represents
+fn caused_by(error: Error, e: &io::Error) -> Error {
+    let kind: BackendErrorKind = match e.kind() {
+        io::ErrorKind::AlreadyExists => BackendErrorKind::DuplicateKey,
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => BackendErrorKind::Timeout,
+        _ => BackendErrorKind::Other,
+    };
+    error.caused_by(kind, e)
+}
+
+/// Get the storage directory configured by rss_storage_dir, expanding ~ to the home directory
+/// The home directories of other users(~user) are not supported, nor is ~ without a home directory
+fn storage_dir(value: &str) -> Option<PathBuf> {
+    let relative: &str = if value == "~" {
+        ""
+    } else if value.starts_with("~/") {
+        value[2..].trim_start_matches('/')
+    } else if value.starts_with('~') {
+        return Option::None;
+    } else {
+        return Option::Some(PathBuf::from(value));
+    };
+
+    env::var("HOME")
+        .ok()
+        .map(|home| PathBuf::from(home).join(relative))
+}
+
+impl_storage!(FileStore, |store| store.clone(), |_| Result::Ok(
+    Report::new(
+        SCOPE.to_string(),
+        "the file store needs no migrations".to_string(),
+    )
+));
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn feed_files_test() {
+        let root: PathBuf = env::temp_dir().join(format!("feeder-test-{}", Uuid::new_v4()));
+        let store: FileStore = FileStore::new(root.clone()).unwrap();
+
+        let feed: model::Feed = store
+            .clone()
+            .create_feed(model::Feed::new("Title", "Description", "https://example.com").unwrap())
+            .unwrap();
+        let feed_dir: PathBuf = store.feed_dir(feed.get_uuid().unwrap());
+        let feed_item: model::FeedItem = store
+            .clone()
+            .create_feed_item(
+                feed.clone(),
+                model::FeedItem::new("Item title", "https://example.com/item", "Description")
+                    .unwrap(),
+            )
+            .unwrap();
+        let item_uuid: Uuid = feed_item.get_uuid().unwrap();
+
+        // The item has its own file and the rendering of the feed covers it
+        assert!(store
+            .item_path(feed.get_uuid().unwrap(), item_uuid)
+            .exists());
+        let rendering: String = fs::read_to_string(feed_dir.join(RENDERING_FILE)).unwrap();
+        assert!(rendering.contains("Item title"));
+        assert_ne!(
+            store
+                .clone()
+                .get_feed_checksum(feed.get_uuid().unwrap())
+                .unwrap(),
+            feed.get_checksum().unwrap()
+        );
+
+        store
+            .clone()
+            .delete_feed_item(feed.clone(), item_uuid, feed_item.get_checksum())
+            .unwrap();
+        assert!(!store
+            .item_path(feed.get_uuid().unwrap(), item_uuid)
+            .exists());
+        let rendering: String = fs::read_to_string(feed_dir.join(RENDERING_FILE)).unwrap();
+        assert!(!rendering.contains("Item title"));
+
+        store
+            .clone()
+            .delete_feed(feed.get_uuid().unwrap(), Option::None)
+            .unwrap();
+        assert!(!feed_dir.exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn storage_dir_test() {
+        let home: PathBuf = PathBuf::from(env::var("HOME").unwrap());
+        assert_eq!(storage_dir("~"), Option::Some(home.clone()));
+        assert_eq!(storage_dir("~/rss"), Option::Some(home.join("rss")));
+        assert_eq!(
+            storage_dir("/srv/rss"),
+            Option::Some(PathBuf::from("/srv/rss"))
+        );
+        assert_eq!(storage_dir("rss/~"), Option::Some(PathBuf::from("rss/~")));
+        assert_eq!(storage_dir("~user/rss"), Option::None);
+    }
+
+    #[test]
+    fn render_due_items_test() {
+        let root: PathBuf = env::temp_dir().join(format!("feeder-test-{}", Uuid::new_v4()));
+        let store: FileStore = FileStore::new(root.clone()).unwrap();
+
+        let feed: model::Feed = store
+            .clone()
+            .create_feed(model::Feed::new("Title", "Description", "https://example.com").unwrap())
+            .unwrap();
+        let feed_dir: PathBuf = store.feed_dir(feed.get_uuid().unwrap());
+        let mut feed_item: model::FeedItem =
+            model::FeedItem::new("Item title", "https://example.com/item", "Description").unwrap();
+        feed_item.published_at = Option::Some(Utc::now() + chrono::Duration::seconds(1));
+        store.clone().create_feed_item(feed, feed_item).unwrap();
+        let rendering: String = fs::read_to_string(feed_dir.join(RENDERING_FILE)).unwrap();
+        assert!(!rendering.contains("Item title"));
+
+        // Listing the feeds, as the sweeper does, renders the items that went live
+        thread::sleep(Duration::from_millis(1500));
+        store.clone().get_feeds().unwrap();
+        let rendering: String = fs::read_to_string(feed_dir.join(RENDERING_FILE)).unwrap();
+        assert!(rendering.contains("Item title"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,591 @@
+use crate::{
+    common::{
+        errors::{FeedDbError, FeedItemDbError},
+        report::Report,
+        DbResult,
+    },
+    create_error, impl_storage,
+};
+
+use crate::db::{
+    model::{
+        aux::{ItemState, RetentionAction},
+        ItemsVec,
+    },
+    query::{FeedQuery, FeedSort, ItemOrder, ItemQuery, Page, Pagination},
+    *,
+};
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use chrono::{DateTime, Utc};
+use log::*;
+use uuid::Uuid;
+
+const SCOPE: &str = "database/inmemory";
+
+/// The feeds and the feed items held in memory, in the order they were created in
+#[derive(Debug, Default)]
+struct Collections {
+    feeds: Vec<model::Feed>,
+    items: Vec<model::FeedItem>,
+}
+
+impl Collections {
+    /// Get the position of a feed
+    fn feed_position(&self, uuid: Uuid) -> Option<usize> {
+        self.feeds
+            .iter()
+            .position(|feed| feed.get_uuid() == Option::Some(uuid))
+    }
+
+    /// Get the position of a feed item of a feed
+    fn item_position(&self, feed_uuid: Uuid, uuid: Uuid) -> Option<usize> {
+        self.items.iter().position(|feed_item| {
+            feed_item.get_uuid() == Option::Some(uuid)
+                && feed_item.get_feed_uuid() == Option::Some(feed_uuid)
+        })
+    }
+
+    /// Get the feed items of a feed
+    fn items_of(&self, feed_uuid: Uuid) -> impl Iterator<Item = &model::FeedItem> {
+        self.items
+            .iter()
+            .filter(move |feed_item| feed_item.get_feed_uuid() == Option::Some(feed_uuid))
+    }
+}
+
+/// A thread-safe store that keeps the feeds and the feed items in the memory of the process
+/// Clones of the store share the same collections, which are lost when the process exits
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore(Arc<RwLock<Collections>>);
+
+impl MemoryStore {
+    /// Lock the collections for reading
+    /// The collections are only changed by single operations, so they are used even if a thread
+    /// panicked while holding the lock
+    fn read(&self) -> RwLockReadGuard<Collections> {
+        self.0.read().unwrap_or_else(|e| {
+            warn!("the in-memory store was poisoned");
+            e.into_inner()
+        })
+    }
+
+    /// Lock the collections for writing
+    fn write(&self) -> RwLockWriteGuard<Collections> {
+        self.0.write().unwrap_or_else(|e| {
+            warn!("the in-memory store was poisoned");
+            e.into_inner()
+        })
+    }
+}
+
+/// Implementation of the FeederWrapper for the in-memory store
+impl FeedWrapper for MemoryStore {
+    fn create_feed(self, feed: model::Feed) -> DbResult<model::Feed> {
+        let mut created_feed: model::Feed = model::Feed::new_from_model(feed)?;
+        created_feed.set_created_at(Utc::now());
+
+        self.write().feeds.push(created_feed.clone());
+        Result::Ok(created_feed)
+    }
+
+    fn get_feed(self, uuid: Uuid) -> DbResult<model::Feed> {
+        let feed: Option<model::Feed> = {
+            let collections = self.read();
+            collections
+                .feed_position(uuid)
+                .map(|index| collections.feeds[index].clone())
+        };
+
+        match feed {
+            Some(value) => publish_due_items(&self, value),
+            None => {
+                warn!("the store has no such feed");
+                Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound))
+            }
+        }
+    }
+
+    fn get_feeds(self) -> DbResult<Vec<model::Feed>> {
+        Result::Ok(self.read().feeds.clone())
+    }
+
+    fn list_feeds(self, query: FeedQuery) -> DbResult<Page<model::Feed>> {
+        let mut feeds: Vec<model::Feed> = self
+            .read()
+            .feeds
+            .iter()
+            .filter(|feed| query.category.is_none() || feed.category == query.category)
+            .filter(|feed| query.language.is_none() || feed.language == query.language)
+            .cloned()
+            .collect();
+
+        // The feeds are kept in the order they were created in, the sort is stable so that order
+        // breaks the ties when sorting by title
+        match query.sort {
+            FeedSort::TitleAscending => feeds.sort_by(|a, b| a.title.cmp(&b.title)),
+            FeedSort::TitleDescending => {
+                feeds.reverse();
+                feeds.sort_by(|a, b| b.title.cmp(&a.title));
+            }
+            FeedSort::CreatedAscending => {}
+            FeedSort::CreatedDescending => feeds.reverse(),
+        }
+
+        // Take one more feed than requested in order to know if there is a next page
+        Result::Ok(Page::from_results(
+            paginate(feeds, &query.pagination),
+            query.pagination.offset,
+            query.pagination.limit,
+        ))
+    }
+
+    fn update_feed(
+        self,
+        uuid: Uuid,
+        mut feed: model::Feed,
+        checksum: Option<String>,
+    ) -> DbResult<model::Feed> {
+        // The uuid may only be in the url, the items of the feed are looked up with it
+        feed.set_uuid(uuid);
+        // Items are stored separately and reference their feed
+        feed.items = Option::None;
+        feed.set_updated_at(Utc::now());
+
+        if let Some(e) = feed.compute_checksum_with_items(self.clone()) {
+            return Result::Err(e);
+        }
+
+        {
+            let mut collections = self.write();
+            let index: usize = match collections.feed_position(uuid) {
+                Some(value) => value,
+                None => {
+                    warn!("the store has no such feed");
+                    return Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound));
+                }
+            };
+            // Compare and set: nothing is updated if the feed changed in the meantime
+            if !checksum_matches(collections.feeds[index].get_checksum(), &checksum) {
+                warn!("the checksum of the feed does not match the expected one");
+                return Result::Err(create_error!(SCOPE, FeedDbError::ChecksumMismatch));
+            }
+
+            feed.keep_managed_fields(&collections.feeds[index]);
+            collections.feeds[index] = feed;
+        }
+
+        self.get_feed(uuid)
+    }
+
+    fn delete_feed(self, _uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>> {
+        let mut collections = self.write();
+        let index: usize = match collections.feed_position(_uuid) {
+            Some(value) => value,
+            None => {
+                warn!("the store has no such feed");
+                return Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound));
+            }
+        };
+        // Compare and delete: nothing is deleted if the feed changed in the meantime
+        if !checksum_matches(collections.feeds[index].get_checksum(), &checksum) {
+            warn!("the checksum of the feed does not match the expected one");
+            return Result::Err(create_error!(SCOPE, FeedDbError::ChecksumMismatch));
+        }
+
+        // Delete the items of the feed as well
+        collections.feeds.remove(index);
+        collections
+            .items
+            .retain(|feed_item| feed_item.get_feed_uuid() != Option::Some(_uuid));
+
+        Result::Ok(Report::new(SCOPE.to_string(), "deleted feed".to_string()))
+    }
+
+    fn get_feed_checksum(self, _uuid: Uuid) -> DbResult<String> {
+        let feed: model::Feed = self.get_feed(_uuid)?;
+        if let Some(value) = feed.get_checksum() {
+            return Result::Ok(value);
+        }
+        warn!("the feed has no checksum");
+        Result::Err(create_error!(SCOPE, FeedDbError::FeedHasNoChecksum))
+    }
+}
+
+/// Implementation of FeedItemWrapper for the in-memory store
+impl FeedItemWrapper for MemoryStore {
+    fn create_feed_item(
+        self,
+        parent_feed: model::Feed,
+        feed_item: model::FeedItem,
+    ) -> DbResult<model::FeedItem> {
+        let mut created_feed_item = model::FeedItem::new_from_model(feed_item)?;
+        created_feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        created_feed_item.set_created_at(Utc::now());
+
+        self.write().items.push(created_feed_item.clone());
+        refresh_feed_checksum(&self, parent_feed)?;
+
+        Result::Ok(created_feed_item)
+    }
+
+    fn get_feed_item(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<model::FeedItem> {
+        let collections = self.read();
+        match collections.item_position(parent_feed.get_uuid().unwrap(), uuid) {
+            Some(index) => Result::Ok(collections.items[index].clone()),
+            None => {
+                warn!("parent feed has no matching feed item");
+                Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound))
+            }
+        }
+    }
+
+    fn get_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Option<Vec<Uuid>>,
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup> {
+        // If no uuids are passed to the function, search for all feed items in this feed
+        // Otherwise, search only for the specified ones
+        // Items are kept in the order they were created in
+        let now: DateTime<Utc> = Utc::now();
+        let items_vec: Vec<model::FeedItem> = self
+            .read()
+            .items_of(parent_feed.get_uuid().unwrap())
+            .filter(|feed_item| match &uuids {
+                Some(uuid_vec) => feed_item
+                    .get_uuid()
+                    .map_or(false, |value| uuid_vec.contains(&value)),
+                None => true,
+            })
+            .filter(|feed_item| include_unpublished || is_published(feed_item, false, now))
+            .cloned()
+            .collect();
+
+        // The requested uuids that were not found are missing
+        let mut missing: Vec<Uuid> = Vec::new();
+        if let Some(uuid_vec) = uuids {
+            let found: HashSet<Uuid> = items_vec
+                .iter()
+                .filter_map(|feed_item| feed_item.get_uuid())
+                .collect();
+            for item_uuid in uuid_vec {
+                if !found.contains(&item_uuid) && !missing.contains(&item_uuid) {
+                    missing.push(item_uuid);
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            warn!("the store has no feed items for {:?}", missing);
+        }
+
+        Result::Ok(ItemsLookup {
+            items: items_vec,
+            missing,
+        })
+    }
+
+    fn list_feed_items(
+        self,
+        parent_feed: model::Feed,
+        query: ItemQuery,
+    ) -> DbResult<Page<model::FeedItem>> {
+        let now: DateTime<Utc> = Utc::now();
+        let mut items_vec: Vec<model::FeedItem> = self
+            .read()
+            .items_of(parent_feed.get_uuid().unwrap())
+            .filter(|feed_item| match query.state {
+                ItemState::Published => is_published(feed_item, query.include_scheduled, now),
+                state => feed_item.get_state() == state,
+            })
+            .cloned()
+            .collect();
+
+        // Items are ordered by their publication time and then by uuid, items without a
+        // publication time come first in chronological order
+        items_vec.sort_by_key(|feed_item| (feed_item.published_at, feed_item.get_uuid()));
+        if query.order == ItemOrder::NewestFirst {
+            items_vec.reverse();
+        }
+
+        // The page starts right after the last item of the previous page
+        if let Some(cursor) = query.pagination.after {
+            items_vec.retain(|feed_item| cursor.precedes(feed_item, query.order));
+        }
+
+        // Take one more item than requested in order to know if there is a next page
+        items_vec.truncate(query.pagination.limit as usize + 1);
+        Result::Ok(Page::from_items(items_vec, query.pagination.limit))
+    }
+
+    fn update_feed_item(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        mut feed_item: model::FeedItem,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        // The item must stay in its feed
+        feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        feed_item.set_updated_at(Utc::now());
+
+        {
+            let mut collections = self.write();
+            let index: usize =
+                match collections.item_position(parent_feed.get_uuid().unwrap(), uuid) {
+                    Some(value) => value,
+                    None => {
+                        warn!("parent feed has no matching feed item");
+                        return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
+                    }
+                };
+            // Compare and set: nothing is updated if the item changed in the meantime
+            if !checksum_matches(collections.items[index].get_checksum(), &checksum) {
+                warn!("the checksum of the feed item does not match the expected one");
+                return Result::Err(create_error!(SCOPE, FeedItemDbError::ChecksumMismatch));
+            }
+
+            // The fields managed by feeder are not updated, the checksum covers them
+            feed_item.keep_managed_fields(&collections.items[index]);
+            if let Some(e) = feed_item.compute_checksum() {
+                warn!("failed to compute the checksum for the feed item");
+                return Result::Err(e);
+            }
+            collections.items[index] = feed_item.clone();
+        }
+
+        refresh_feed_checksum(&self, parent_feed)?;
+        Result::Ok(feed_item)
+    }
+
+    /// Delete a feed item
+    fn delete_feed_item(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        checksum: Option<String>,
+    ) -> DbResult<Report<String>> {
+        {
+            let mut collections = self.write();
+            let index: usize =
+                match collections.item_position(parent_feed.get_uuid().unwrap(), uuid) {
+                    Some(value) => value,
+                    None => {
+                        warn!("parent feed has no matching feed item");
+                        return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
+                    }
+                };
+            // Compare and delete: nothing is deleted if the item changed in the meantime
+            if !checksum_matches(collections.items[index].get_checksum(), &checksum) {
+                warn!("the checksum of the feed item does not match the expected one");
+                return Result::Err(create_error!(SCOPE, FeedItemDbError::ChecksumMismatch));
+            }
+
+            collections.items.remove(index);
+        }
+
+        refresh_feed_checksum(&self, parent_feed)?;
+
+        Result::Ok(Report::new(
+            SCOPE.to_string(),
+            "deleted feed item".to_string(),
+        ))
+    }
+
+    /// Move a feed item to another stage of its lifecycle
+    fn set_feed_item_state(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        state: ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        let feed_item: model::FeedItem;
+        {
+            let mut collections = self.write();
+            let index: usize =
+                match collections.item_position(parent_feed.get_uuid().unwrap(), uuid) {
+                    Some(value) => value,
+                    None => {
+                        warn!("parent feed has no matching feed item");
+                        return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
+                    }
+                };
+
+            let mut moved_feed_item: model::FeedItem = collections.items[index].clone();
+            if let Some(e) = moved_feed_item.transition(state) {
+                return Result::Err(e);
+            }
+            moved_feed_item.set_updated_at(Utc::now());
+
+            // Compare and set: nothing is moved if the item changed in the meantime
+            if !checksum_matches(collections.items[index].get_checksum(), &checksum) {
+                warn!("the checksum of the feed item does not match the expected one");
+                return Result::Err(create_error!(SCOPE, FeedItemDbError::ChecksumMismatch));
+            }
+
+            collections.items[index] = moved_feed_item.clone();
+            feed_item = moved_feed_item;
+        }
+
+        refresh_feed_checksum(&self, parent_feed)?;
+        Result::Ok(feed_item)
+    }
+
+    /// Get the checksum of a feed item
+    fn get_feed_item_checksum(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<String> {
+        let feed_item: model::FeedItem;
+
+        // If the feed does not have such item, error
+        match self.get_feed_item(parent_feed, uuid) {
+            Ok(value) => feed_item = value,
+            Err(e) => {
+                warn!("parent feed does not have such item: {:?}", e);
+                return Result::Err(e);
+            }
+        }
+
+        if let Some(value) = feed_item.get_checksum() {
+            return Result::Ok(value);
+        }
+        Result::Err(create_error!(SCOPE, FeedItemDbError::NoChecksum))
+    }
+
+    /// Delete or archive some published feed items at once
+    fn prune_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Vec<Uuid>,
+        action: RetentionAction,
+    ) -> DbResult<Vec<Uuid>> {
+        let feed_uuid: Option<Uuid> = parent_feed.get_uuid();
+        let mut pruned: Vec<Uuid> = Vec::new();
+        {
+            let mut collections = self.write();
+            let now: DateTime<Utc> = Utc::now();
+            let is_pruned = |feed_item: &model::FeedItem| {
+                feed_item.get_feed_uuid() == feed_uuid
+                    && feed_item.get_state() == ItemState::Published
+                    && feed_item
+                        .get_uuid()
+                        .map_or(false, |value| uuids.contains(&value))
+            };
+
+            match action {
+                RetentionAction::Delete => collections.items.retain(|feed_item| {
+                    if is_pruned(feed_item) {
+                        pruned.push(feed_item.get_uuid().unwrap());
+                        return false;
+                    }
+                    true
+                }),
+                RetentionAction::Archive => {
+                    for feed_item in collections.items.iter_mut() {
+                        // Published items can always be archived
+                        if is_pruned(feed_item)
+                            && feed_item.transition(ItemState::Archived).is_none()
+                        {
+                            feed_item.set_updated_at(now);
+                            pruned.push(feed_item.get_uuid().unwrap());
+                        }
+                    }
+                }
+            }
+        }
+
+        if !pruned.is_empty() {
+            refresh_feed_checksum(&self, parent_feed)?;
+        }
+        Result::Ok(pruned)
+    }
+}
+
+/// Check the stored checksum of a model against the expected one, if there is one
+fn checksum_matches(stored: Option<String>, expected: &Option<String>) -> bool {
+    expected.is_none() || stored == *expected
+}
+
+/// Check whether a feed item is published
+/// Scheduled items are published once their publication time passed
+fn is_published(feed_item: &model::FeedItem, include_scheduled: bool, now: DateTime<Utc>) -> bool {
+    feed_item.get_state() == ItemState::Published
+        && (include_scheduled || feed_item.published_at.map_or(true, |value| value <= now))
+}
+
+/// Take the results of a page, along with one more result if there is a next page
+fn paginate<T>(results: Vec<T>, pagination: &Pagination) -> Vec<T> {
+    results
+        .into_iter()
+        .skip(pagination.offset as usize)
+        .take(pagination.limit as usize + 1)
+        .collect()
+}
+
+/// Recompute the checksum of a feed after its items changed
+/// The stored feed is refreshed while the store is locked, so that concurrent changes to the feed
+/// are not overwritten
+fn refresh_feed_checksum(store: &MemoryStore, feed: model::Feed) -> DbResult<model::Feed> {
+    let now: DateTime<Utc> = Utc::now();
+    let feed_uuid: Uuid = feed.get_uuid().unwrap();
+
+    let mut collections = store.write();
+    let items_vec: Vec<model::FeedItem> = collections
+        .items_of(feed_uuid)
+        .filter(|feed_item| is_published(feed_item, false, now))
+        .cloned()
+        .collect();
+    let index: usize = match collections.feed_position(feed_uuid) {
+        Some(value) => value,
+        None => {
+            // The feed was deleted in the meantime, so there is nothing to refresh
+            return Result::Ok(feed);
+        }
+    };
+
+    let mut refreshed_feed: model::Feed = collections.feeds[index].clone();
+    refreshed_feed.set_updated_at(now);
+    refreshed_feed.items = Option::Some(ItemsVec::Full(items_vec));
+    if let Some(e) = refreshed_feed.compute_checksum() {
+        return Result::Err(e);
+    }
+    refreshed_feed.items = Option::None;
+
+    collections.feeds[index] = refreshed_feed.clone();
+    Result::Ok(refreshed_feed)
+}
+
+/// Refresh the checksum of a feed if some of its scheduled items went live since it was updated
+/// Scheduled items are not covered by the checksum of the feed, so it changes the first time the
+/// feed is read after one of them is published
+fn publish_due_items(store: &MemoryStore, feed: model::Feed) -> DbResult<model::Feed> {
+    let now: DateTime<Utc> = Utc::now();
+    let updated_at: Option<DateTime<Utc>> = feed.get_updated_at();
+
+    let due: bool = store
+        .read()
+        .items_of(feed.get_uuid().unwrap())
+        .filter_map(|feed_item| feed_item.published_at)
+        .any(|published_at| Option::Some(published_at) > updated_at && published_at <= now);
+    if !due {
+        return Result::Ok(feed);
+    }
+
+    info!(
+        "publishing the scheduled items of feed {:?}",
+        feed.get_uuid()
+    );
+    refresh_feed_checksum(store, feed)
+}
+
+impl_storage!(MemoryStore, |store| store.clone(), |_| Result::Ok(
+    Report::new(
+        SCOPE.to_string(),
+        "the in-memory store needs no migrations".to_string(),
+    )
+));
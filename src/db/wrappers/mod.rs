@@ -1,13 +1,31 @@
+#[cfg(not(any(
+    feature = "mongo",
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "inmemory",
+    feature = "filesystem"
+)))]
+compile_error!(
+    "feeder needs a storage backend, enable at least one of the mongo, sqlite, postgres, inmemory \
+     and filesystem features"
+);
+
 // Mongodb implementation for feeder
 #[cfg(feature = "mongo")]
 pub mod mongo;
 
-/// Mongodb implementation imports
-#[cfg(feature = "mongo")]
-use {mongodb::db::DatabaseInner, std::sync::Arc};
+// Sqlite implementation for feeder
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
-/// Mongodb struct used for connections to the database
-#[cfg(feature = "mongo")]
-#[database("feeder")]
-#[derive(Clone)]
-pub struct DbConnection(Arc<DatabaseInner>);
+// Postgres implementation for feeder
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+// In-memory implementation for feeder, used for tests and demos
+#[cfg(feature = "inmemory")]
+pub mod inmemory;
+
+// Flat-file implementation for feeder, stored in rss_storage_dir
+#[cfg(feature = "filesystem")]
+pub mod filesystem;
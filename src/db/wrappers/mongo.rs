@@ -1,31 +1,49 @@
 use crate::{
     common::{
-        errors::{FeedDbError, FeedItemDbError},
+        errors::{BackendErrorKind, Error, FeedDbError, FeedItemDbError, ItemStateError},
         report::Report,
-        DbResult,
+        time, DbResult,
     },
-    create_error, option_to_result,
+    create_error, impl_storage,
 };
 
-use crate::db::*;
+use crate::db::{
+    query::{FeedQuery, FeedSort, ItemCursor, ItemOrder, ItemQuery, Page},
+    *,
+};
+
+use std::{collections::HashSet, io};
 
+use chrono::Utc;
 use log::*;
-use mongodb::Document;
+use mongodb::{db::ThreadedDatabase, Document};
+use rocket::Config;
+use rocket_contrib::databases::{r2d2::Pool, r2d2_mongodb::MongodbConnectionManager};
 use uuid::Uuid;
 use wither::prelude::*;
 
 const SCOPE: &str = "database/mongo";
 
+/// The optional fields of a feed, which are missing from its document when they are not set
+const FEED_OPTIONAL_FIELDS: &[&str] = &["category", "copyright", "image", "language", "retention"];
+/// The optional fields of a feed item, which are missing from its document when they are not set
+/// The publication time is not one of them, items keep it unless they are given another one
+const ITEM_OPTIONAL_FIELDS: &[&str] = &["author", "comments", "enclosure"];
+
 /// Implementation of the FeederWrapper for MongoDb
 impl FeedWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
     fn create_feed(self, feed: model::Feed) -> DbResult<model::Feed> {
         let mut created_feed: model::Feed = model::Feed::new_from_model(feed)?;
+        created_feed.set_created_at(Utc::now());
 
         match created_feed.save(self.clone(), Option::None) {
             Ok(_) => Result::Ok(created_feed),
             Err(e) => {
                 warn!("error while saving feed: {:?}", e);
-                Result::Err(create_error!(SCOPE, FeedDbError::FailedToSaveFeed))
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToSaveFeed),
+                    &e,
+                ))
             }
         }
     }
@@ -38,21 +56,93 @@ impl FeedWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
         match model::Feed::find_one(self.clone(), Option::Some(filter), Option::None) {
             Ok(value) => {
                 if let Some(feed) = value {
-                    Result::Ok(feed)
+                    publish_due_items(self, feed)
                 } else {
                     warn!("the database returned no feed");
-                    Result::Err(create_error!(SCOPE, FeedDbError::FailedToGetFeeds))
+                    Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound))
                 }
             }
             Err(e) => {
                 warn!("failed to get the feed: {:?}", e);
-                Result::Err(create_error!(SCOPE, FeedDbError::FailedToGetFeeds))
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToGetFeeds),
+                    &e,
+                ))
             }
         }
     }
 
-    fn update_feed(self, uuid: Uuid, mut feed: model::Feed) -> DbResult<model::Feed> {
-        if let Some(e) = feed.compute_checksum(Option::Some(self.clone())) {
+    fn get_feeds(self) -> DbResult<Vec<model::Feed>> {
+        match model::Feed::find(self.clone(), Option::None, Option::None) {
+            Ok(value) => Result::Ok(value),
+            Err(e) => {
+                warn!("failed to get the feeds: {:?}", e);
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToGetFeeds),
+                    &e,
+                ))
+            }
+        }
+    }
+
+    fn list_feeds(self, query: FeedQuery) -> DbResult<Page<model::Feed>> {
+        let mut filter: Document = Document::new();
+        if let Some(category) = query.category {
+            filter.insert("category", category);
+        }
+        if let Some(language) = query.language {
+            filter.insert("language", language);
+        }
+
+        // The object id starts with the creation timestamp, so it is used for sorting by
+        // creation time and as a tie breaker for sorting by title
+        let sort: Document = match query.sort {
+            FeedSort::TitleAscending => doc! {"title": 1, "_id": 1},
+            FeedSort::TitleDescending => doc! {"title": -1, "_id": -1},
+            FeedSort::CreatedAscending => doc! {"_id": 1},
+            FeedSort::CreatedDescending => doc! {"_id": -1},
+        };
+
+        // Fetch one more feed than requested in order to know if there is a next page
+        let mut find_options: mongodb::coll::options::FindOptions;
+        find_options = mongodb::coll::options::FindOptions::new();
+        find_options.sort = Option::Some(sort);
+        find_options.skip = Option::Some(query.pagination.offset as i64);
+        find_options.limit = Option::Some(i64::from(query.pagination.limit) + 1);
+
+        match model::Feed::find(
+            self.clone(),
+            Option::Some(filter),
+            Option::Some(find_options),
+        ) {
+            Ok(value) => Result::Ok(Page::from_results(
+                value,
+                query.pagination.offset,
+                query.pagination.limit,
+            )),
+            Err(e) => {
+                warn!("failed to list the feeds: {:?}", e);
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToGetFeeds),
+                    &e,
+                ))
+            }
+        }
+    }
+
+    fn update_feed(
+        self,
+        uuid: Uuid,
+        mut feed: model::Feed,
+        checksum: Option<String>,
+    ) -> DbResult<model::Feed> {
+        // The uuid may only be in the url, the items of the feed are looked up with it
+        feed.set_uuid(uuid);
+        // Items are stored separately and reference their feed
+        feed.items = Option::None;
+        feed.set_updated_at(Utc::now());
+
+        if let Some(e) = feed.compute_checksum_with_items(self.clone()) {
             return Result::Err(e);
         }
 
@@ -62,11 +152,14 @@ impl FeedWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
             Ok(value) => update_bson = value,
             Err(e) => {
                 warn!("failed to encode feed into bson: {:?}", e);
-                return Result::Err(create_error!(SCOPE, FeedDbError::FailedToUpdateFeed));
+                return Result::Err(
+                    create_error!(SCOPE, FeedDbError::FailedToUpdateFeed)
+                        .caused_by(BackendErrorKind::Other, &e),
+                );
             }
         }
         match update_bson.as_document() {
-            Some(value) => update = doc! {"$set": value.clone()},
+            Some(value) => update = update_of(value, FEED_OPTIONAL_FIELDS),
             None => {
                 warn!("failed to get the bson-encoded feed as a document");
                 return Result::Err(create_error!(SCOPE, FeedDbError::FailedToUpdateFeed));
@@ -78,9 +171,14 @@ impl FeedWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
         find_and_update_options.return_document =
             Option::Some(mongodb::coll::options::ReturnDocument::After);
 
-        let filter: Document = doc! {
+        let mut filter: Document = doc! {
             "uuid": format!("{}", uuid)
         };
+        // Compare and set: nothing is updated if the feed changed in the meantime
+        let conditional: bool = checksum.is_some();
+        if let Some(value) = checksum {
+            filter.insert("checksum", value);
+        }
 
         match model::Feed::find_one_and_update(
             self.clone(),
@@ -88,33 +186,60 @@ impl FeedWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
             update,
             Option::Some(find_and_update_options),
         ) {
-            Ok(value) => option_to_result!(value, SCOPE, FeedDbError::FailedToUpdateFeed),
+            Ok(Some(value)) => Result::Ok(value),
+            Ok(None) => Result::Err(feed_not_matched(self, uuid, conditional)),
             Err(e) => {
                 warn!("error updating the feed: {:?}", e);
-                Result::Err(create_error!(SCOPE, FeedDbError::FailedToUpdateFeed))
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+                    &e,
+                ))
             }
         }
     }
 
-    fn delete_feed(self, _uuid: Uuid) -> DbResult<Report<String>> {
-        let filter: Document = doc! {
+    fn delete_feed(self, _uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>> {
+        let mut filter: Document = doc! {
             "uuid": format!("{}", _uuid)
         };
+        // Compare and delete: nothing is deleted if the feed changed in the meantime
+        let conditional: bool = checksum.is_some();
+        if let Some(value) = checksum {
+            filter.insert("checksum", value);
+        }
 
         match model::Feed::find_one_and_delete(self.clone(), filter, Option::None) {
             Ok(value) => {
                 if value.is_none() {
                     warn!("the database did not return the old feed after deleting");
-                    Result::Err(create_error!(SCOPE, FeedDbError::FailedToDeleteFeed))
-                } else {
-                    Result::Ok(Report::new(SCOPE.to_string(), "deleted feed".to_string()))
+                    return Result::Err(feed_not_matched(self, _uuid, conditional));
                 }
             }
             Err(e) => {
                 warn!("failed to delete the feed: {:?}", e);
-                Result::Err(create_error!(SCOPE, FeedDbError::FailedToDeleteFeed))
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToDeleteFeed),
+                    &e,
+                ));
             }
         }
+
+        // Delete the items of the feed as well
+        let items_filter: Document = doc! {
+            "feed_uuid": format!("{}", _uuid)
+        };
+        if let Err(e) = self
+            .collection(model::FeedItem::COLLECTION_NAME)
+            .delete_many(items_filter, Option::None)
+        {
+            warn!("failed to delete the items of the deleted feed: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToDeleteFeed),
+                &e,
+            ));
+        }
+
+        Result::Ok(Report::new(SCOPE.to_string(), "deleted feed".to_string()))
     }
 
     fn get_feed_checksum(self, _uuid: Uuid) -> DbResult<String> {
@@ -131,149 +256,175 @@ impl FeedWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
 impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
     fn create_feed_item(
         self,
-        mut parent_feed: model::Feed,
+        parent_feed: model::Feed,
         feed_item: model::FeedItem,
     ) -> DbResult<model::FeedItem> {
         let mut created_feed_item = model::FeedItem::new_from_model(feed_item)?;
+        created_feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        created_feed_item.set_created_at(Utc::now());
 
         if let Err(e) = created_feed_item.save(self.clone(), Option::None) {
             warn!("failed to save feed item in the database: {:?}", e);
-            return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToSaveItem));
-        }
-
-        if parent_feed.items.is_none() {
-            info!("parent feed did not have any items, creating the items list now");
-            parent_feed.items = Option::Some(model::ItemsVec::Uuid(Vec::new()));
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+                &e,
+            ));
         }
 
-        let mut items_vec: Vec<Uuid>;
-        match parent_feed.items.clone().unwrap() {
-            model::ItemsVec::Uuid(value) => items_vec = value,
-            model::ItemsVec::Full(_) => {
-                parent_feed.with_uuids();
-                if let model::ItemsVec::Uuid(_value) = parent_feed.items.unwrap() {
-                    info!("parent feed had the full items, changed to uuids only");
-                    items_vec = _value;
-                } else {
-                    warn!("failed to change the parent feed to have uuids only");
-                    return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToSaveItem));
-                }
-            }
-        }
-        items_vec.push(created_feed_item.get_uuid().unwrap());
-        parent_feed.items = Option::Some(model::ItemsVec::Uuid(items_vec));
-        self.update_feed(parent_feed.get_uuid().unwrap(), parent_feed)?;
+        refresh_feed_checksum(self, parent_feed)?;
 
         Result::Ok(created_feed_item)
     }
 
-    fn get_feed_item(self, mut parent_feed: model::Feed, uuid: Uuid) -> DbResult<model::FeedItem> {
-        if parent_feed.items.is_none() {
-            return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
-        }
+    fn get_feed_item(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<model::FeedItem> {
+        let filter: Document = doc! {
+            "uuid": format!("{}", uuid),
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap())
+        };
 
-        let items_vec: Vec<Uuid>;
-        match parent_feed.items.clone().unwrap() {
-            model::ItemsVec::Uuid(value) => items_vec = value,
-            model::ItemsVec::Full(_) => {
-                parent_feed.with_uuids();
-                if let model::ItemsVec::Uuid(_value) = parent_feed.items.unwrap() {
-                    info!("parent feed had the full items, changed to uuids only");
-                    items_vec = _value;
+        match model::FeedItem::find_one(self.clone(), Option::Some(filter), Option::None) {
+            Ok(value) => {
+                if let Some(feed_item) = value {
+                    Result::Ok(feed_item)
                 } else {
-                    warn!("failed to change the parent feed to have uuids only");
-                    return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
+                    warn!("parent feed has no matching feed item");
+                    Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound))
                 }
             }
-        }
-
-        for item_uuid in items_vec {
-            if item_uuid == uuid {
-                let filter: Document = doc! {
-                    "uuid": format!("{}", uuid)
-                };
-
-                match model::FeedItem::find_one(self.clone(), Option::Some(filter), Option::None) {
-                    Ok(value) => {
-                        if let Some(feed_item) = value {
-                            return Result::Ok(feed_item);
-                        } else {
-                            warn!("the database returned no feed item");
-                            return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
-                        }
-                    }
-                    Err(e) => {
-                        warn!("failed to get the feed item: {:?}", e);
-                        return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
-                    }
-                }
+            Err(e) => {
+                warn!("failed to get the feed item: {:?}", e);
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                    &e,
+                ))
             }
         }
-
-        warn!("parent feed has no matching feed item");
-        Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound))
     }
 
     fn get_feed_items(
         self,
-        mut parent_feed: model::Feed,
+        parent_feed: model::Feed,
         uuids: Option<Vec<Uuid>>,
-    ) -> DbResult<Vec<model::FeedItem>> {
-        // If parent feed has no items, error
-        if parent_feed.items.is_none() {
-            return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
-        }
-
-        // Get the item uuids of this feed
-        let parent_item_uuids: Vec<Uuid>;
-        match parent_feed.items.clone().unwrap() {
-            model::ItemsVec::Uuid(value) => parent_item_uuids = value,
-            model::ItemsVec::Full(_) => {
-                parent_feed.with_uuids();
-                if let model::ItemsVec::Uuid(_value) = parent_feed.items.clone().unwrap() {
-                    info!("parent feed had the full items, changed to uuids only");
-                    parent_item_uuids = _value;
-                } else {
-                    warn!("failed to change the parent feed to have uuids only");
-                    return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
-                }
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup> {
+        // If no uuids are passed to the function, search for all feed items in this feed
+        // Otherwise, search only for the specified ones
+        let mut filter: Document = doc! {
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap())
+        };
+        if !include_unpublished {
+            filter_published(&mut filter, false);
+        }
+        if let Some(uuid_vec) = &uuids {
+            // If there are no items, return an empty vector
+            if uuid_vec.is_empty() {
+                return Result::Ok(ItemsLookup {
+                    items: Vec::new(),
+                    missing: Vec::new(),
+                });
             }
+
+            let uuid_strings: Vec<mongodb::Bson> = uuid_vec
+                .iter()
+                .map(|item_uuid| mongodb::Bson::String(format!("{}", item_uuid)))
+                .collect();
+            filter.insert("uuid", doc! {"$in": uuid_strings});
         }
 
-        // Get the item uuids that should be retrieved
-        // If no uuids are passed to the function, search for all feed items in this feed
-        // Otherwise, trim the feed item uuid list to the ones specifiedj
-        let mut item_uuids: Vec<Uuid>;
+        // Items are kept in the order they were created in
+        let mut find_options: mongodb::coll::options::FindOptions;
+        find_options = mongodb::coll::options::FindOptions::new();
+        find_options.sort = Option::Some(doc! {"_id": 1});
+
+        let items_vec: Vec<model::FeedItem>;
+        match model::FeedItem::find(
+            self.clone(),
+            Option::Some(filter),
+            Option::Some(find_options),
+        ) {
+            Ok(value) => items_vec = value,
+            Err(e) => {
+                warn!("failed to get the feed items: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                    &e,
+                ));
+            }
+        }
+
+        // The requested uuids that were not found are missing
+        let mut missing: Vec<Uuid> = Vec::new();
         if let Some(uuid_vec) = uuids {
-            item_uuids = Vec::new();
+            let found: HashSet<Uuid> = items_vec
+                .iter()
+                .filter_map(|feed_item| feed_item.get_uuid())
+                .collect();
             for item_uuid in uuid_vec {
-                if parent_item_uuids.contains(&item_uuid) {
-                    item_uuids.push(item_uuid);
+                if !found.contains(&item_uuid) && !missing.contains(&item_uuid) {
+                    missing.push(item_uuid);
                 }
             }
-        } else {
-            item_uuids = parent_item_uuids;
         }
 
-        let mut items_vec: Vec<model::FeedItem> = Vec::new();
-        // If there are no items, return an empty vector
-        if item_uuids.len() == 0 {
-            return Result::Ok(items_vec);
+        if !missing.is_empty() {
+            warn!("the database returned no feed items for {:?}", missing);
         }
-        // If there is more than one item, iterate over 1..n-1 feed items
-        if item_uuids.len() > 1 {
-            for index in 0..item_uuids.len() - 2 {
-                let feed_item: model::FeedItem = self
-                    .clone()
-                    .get_feed_item(parent_feed.clone(), *item_uuids.get(index).unwrap())?;
-                items_vec.push(feed_item);
+
+        Result::Ok(ItemsLookup {
+            items: items_vec,
+            missing,
+        })
+    }
+
+    fn list_feed_items(
+        self,
+        parent_feed: model::Feed,
+        query: ItemQuery,
+    ) -> DbResult<Page<model::FeedItem>> {
+        let mut filter: Document = doc! {
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap())
+        };
+        match query.state {
+            model::aux::ItemState::Published => {
+                filter_published(&mut filter, query.include_scheduled)
             }
+            state => {
+                filter.insert("state", format!("{}", state));
+            }
+        }
+
+        // The page starts right after the last item of the previous page
+        if let Some(cursor) = query.pagination.after {
+            filter.insert("$or", after_cursor(cursor, query.order));
         }
-        let feed_item: model::FeedItem =
-            self.get_feed_item(parent_feed, *item_uuids.last().unwrap())?;
-        items_vec.push(feed_item);
 
-        Result::Ok(items_vec)
+        // Items are ordered by their publication time and then by uuid, items without a
+        // publication time come first in chronological order
+        let sort: Document = match query.order {
+            ItemOrder::NewestFirst => doc! {"published_at": -1, "uuid": -1},
+            ItemOrder::OldestFirst => doc! {"published_at": 1, "uuid": 1},
+        };
+
+        // Fetch one more item than requested in order to know if there is a next page
+        let mut find_options: mongodb::coll::options::FindOptions;
+        find_options = mongodb::coll::options::FindOptions::new();
+        find_options.sort = Option::Some(sort);
+        find_options.limit = Option::Some(i64::from(query.pagination.limit) + 1);
+
+        match model::FeedItem::find(
+            self.clone(),
+            Option::Some(filter),
+            Option::Some(find_options),
+        ) {
+            Ok(value) => Result::Ok(Page::from_items(value, query.pagination.limit)),
+            Err(e) => {
+                warn!("failed to list the feed items: {:?}", e);
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                    &e,
+                ))
+            }
+        }
     }
 
     fn update_feed_item(
@@ -281,16 +432,15 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
         parent_feed: model::Feed,
         uuid: Uuid,
         mut feed_item: model::FeedItem,
+        checksum: Option<String>,
     ) -> DbResult<model::FeedItem> {
-        // If the feed does not have such item, error
-        if self
-            .clone()
-            .get_feed_item(parent_feed.clone(), uuid.clone())
-            .is_err()
-        {
-            warn!("parent feed does now have such item");
-            return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToUpdate));
-        }
+        // The item must stay in its feed
+        feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        feed_item.set_updated_at(Utc::now());
+
+        // The fields managed by feeder are kept from the stored item
+        let stored: model::FeedItem = self.clone().get_feed_item(parent_feed.clone(), uuid)?;
+        feed_item.keep_managed_fields(&stored);
 
         // Compute the new checksum
         if let Some(e) = feed_item.compute_checksum() {
@@ -304,11 +454,14 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
             Ok(value) => update_bson = value,
             Err(e) => {
                 warn!("failed to encode feed item into bson: {:?}", e);
-                return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToUpdate));
+                return Result::Err(
+                    create_error!(SCOPE, FeedItemDbError::FailedToUpdate)
+                        .caused_by(BackendErrorKind::Other, &e),
+                );
             }
         }
         match update_bson.as_document() {
-            Some(value) => update = doc! {"$set": value.clone()},
+            Some(value) => update = update_of(value, ITEM_OPTIONAL_FIELDS),
             None => {
                 warn!("failed to get the bson-encoded feed as a document");
                 return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToUpdate));
@@ -320,9 +473,16 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
         find_and_update_options.return_document =
             Option::Some(mongodb::coll::options::ReturnDocument::After);
 
-        let filter: Document = doc! {
-            "uuid": format!("{}", uuid)
+        // If the feed does not have such item, nothing is updated
+        let mut filter: Document = doc! {
+            "uuid": format!("{}", uuid),
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap())
         };
+        // Compare and set: nothing is updated if the item changed in the meantime
+        let conditional: bool = checksum.is_some();
+        if let Some(value) = checksum {
+            filter.insert("checksum", value);
+        }
 
         let updated_feed_item: model::FeedItem;
         match model::FeedItem::find_one_and_update(
@@ -335,75 +495,56 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
                 if let Some(_value) = value {
                     updated_feed_item = _value;
                 } else {
-                    return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToUpdate));
+                    return Result::Err(item_not_matched(self, parent_feed, uuid, conditional));
                 }
             }
             Err(e) => {
                 warn!("error updating the feed item: {:?}", e);
-                return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToUpdate));
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+                    &e,
+                ));
             }
         }
 
-        self.update_feed(parent_feed.get_uuid().unwrap(), parent_feed)?;
+        refresh_feed_checksum(self, parent_feed)?;
         Result::Ok(updated_feed_item)
     }
 
     /// Delete a feed item
     fn delete_feed_item(
         self,
-        mut parent_feed: model::Feed,
+        parent_feed: model::Feed,
         uuid: Uuid,
+        checksum: Option<String>,
     ) -> DbResult<Report<String>> {
-        // If the feed does not have such item, error
-        if self
-            .clone()
-            .get_feed_item(parent_feed.clone(), uuid.clone())
-            .is_err()
-        {
-            warn!("parent feed does not have such item");
-            return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToDelete));
-        }
-
-        let filter: Document = doc! {
-            "uuid": format!("{}", uuid.clone())
+        // If the feed does not have such item, nothing is deleted
+        let mut filter: Document = doc! {
+            "uuid": format!("{}", uuid),
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap())
         };
+        // Compare and delete: nothing is deleted if the item changed in the meantime
+        let conditional: bool = checksum.is_some();
+        if let Some(value) = checksum {
+            filter.insert("checksum", value);
+        }
 
         match model::FeedItem::find_one_and_delete(self.clone(), filter, Option::None) {
             Ok(value) => {
                 if value.is_none() {
-                    warn!("the database did not return the old feed item after deleting");
-                    return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToDelete));
+                    return Result::Err(item_not_matched(self, parent_feed, uuid, conditional));
                 }
             }
             Err(e) => {
                 warn!("failed to delete the feed item: {:?}", e);
-                return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToDelete));
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                    &e,
+                ));
             }
         }
 
-        // Get the item uuids of this feed
-        let mut parent_item_uuids: Vec<Uuid>;
-        match parent_feed.items.clone().unwrap() {
-            model::ItemsVec::Uuid(value) => parent_item_uuids = value,
-            model::ItemsVec::Full(_) => {
-                parent_feed.with_uuids();
-                if let model::ItemsVec::Uuid(_value) = parent_feed.items.unwrap() {
-                    info!("parent feed had the full items, changed to uuids only");
-                    parent_item_uuids = _value;
-                } else {
-                    warn!("failed to change the parent feed to have uuids only");
-                    return Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound));
-                }
-            }
-        }
-        for (index, item) in parent_item_uuids.iter().enumerate() {
-            if *item == uuid {
-                parent_item_uuids.remove(index);
-                break;
-            }
-        }
-        parent_feed.items = Option::Some(model::ItemsVec::Uuid(parent_item_uuids));
-        self.update_feed(parent_feed.get_uuid().unwrap(), parent_feed)?;
+        refresh_feed_checksum(self, parent_feed)?;
 
         Result::Ok(Report::new(
             SCOPE.to_string(),
@@ -411,6 +552,83 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
         ))
     }
 
+    /// Move a feed item to another stage of its lifecycle
+    fn set_feed_item_state(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        state: model::aux::ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        let mut feed_item: model::FeedItem =
+            self.clone().get_feed_item(parent_feed.clone(), uuid)?;
+        let previous_state: model::aux::ItemState = feed_item.get_state();
+        if let Some(e) = feed_item.transition(state) {
+            return Result::Err(e);
+        }
+        feed_item.set_updated_at(Utc::now());
+
+        // Publishing a draft can change its publication time, and the checksum covers the state
+        let mut fields: Document = doc! {
+            "state": format!("{}", feed_item.get_state()),
+            "updated_at": time::to_rfc3339(&feed_item.get_updated_at().unwrap())
+        };
+        if let Some(value) = feed_item.published_at {
+            fields.insert("published_at", time::to_rfc3339(&value));
+        }
+        if let Some(value) = feed_item.get_checksum() {
+            fields.insert("checksum", value);
+        }
+        let update: Document = doc! {"$set": fields};
+
+        // If the feed does not have such item, nothing is updated
+        let mut filter: Document = doc! {
+            "uuid": format!("{}", uuid),
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap())
+        };
+        // Nothing is updated either if another transition happened in the meantime, items stored
+        // without a state being published
+        if previous_state == model::aux::ItemState::Published {
+            filter.insert(
+                "state",
+                doc! {"$in": [format!("{}", previous_state), mongodb::Bson::Null]},
+            );
+        } else {
+            filter.insert("state", format!("{}", previous_state));
+        }
+        let conditional: bool = checksum.is_some();
+        if let Some(value) = checksum {
+            filter.insert("checksum", value);
+        }
+
+        match self
+            .collection(model::FeedItem::COLLECTION_NAME)
+            .update_one(filter, update, Option::None)
+        {
+            Ok(value) => {
+                if value.matched_count == 0 {
+                    return Result::Err(state_not_matched(
+                        self,
+                        parent_feed,
+                        uuid,
+                        previous_state,
+                        conditional,
+                    ));
+                }
+            }
+            Err(e) => {
+                warn!("failed to change the state of the feed item: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+                    &e,
+                ));
+            }
+        }
+
+        refresh_feed_checksum(self, parent_feed)?;
+        Result::Ok(feed_item)
+    }
+
     /// Get the checksum of a feed item
     fn get_feed_item_checksum(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<String> {
         let feed_item: model::FeedItem;
@@ -420,7 +638,7 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
             Ok(value) => feed_item = value,
             Err(e) => {
                 warn!("parent feed does not have such item: {:?}", e);
-                return Result::Err(create_error!(SCOPE, FeedItemDbError::FailedToDelete));
+                return Result::Err(e);
             }
         }
 
@@ -429,4 +647,521 @@ impl FeedItemWrapper for std::sync::Arc<mongodb::db::DatabaseInner> {
         }
         Result::Err(create_error!(SCOPE, FeedItemDbError::NoChecksum))
     }
+
+    /// Delete or archive some published feed items at once
+    fn prune_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Vec<Uuid>,
+        action: model::aux::RetentionAction,
+    ) -> DbResult<Vec<Uuid>> {
+        let uuid_strings: Vec<mongodb::Bson> = uuids
+            .iter()
+            .map(|item_uuid| mongodb::Bson::String(format!("{}", item_uuid)))
+            .collect();
+        let filter: Document = doc! {
+            "uuid": {"$in": uuid_strings},
+            "feed_uuid": format!("{}", parent_feed.get_uuid().unwrap()),
+            "state": {"$nin": [
+                format!("{}", model::aux::ItemState::Draft),
+                format!("{}", model::aux::ItemState::Archived)
+            ]}
+        };
+
+        let found: Vec<model::FeedItem>;
+        match model::FeedItem::find(self.clone(), Option::Some(filter.clone()), Option::None) {
+            Ok(value) => found = value,
+            Err(e) => {
+                warn!("failed to get the feed items to prune: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                    &e,
+                ));
+            }
+        }
+        let pruned: Vec<Uuid> = found
+            .iter()
+            .filter_map(|feed_item| feed_item.get_uuid())
+            .collect();
+        if pruned.is_empty() {
+            return Result::Ok(pruned);
+        }
+
+        let collection = self.collection(model::FeedItem::COLLECTION_NAME);
+        let result: mongodb::Result<()> = match action {
+            model::aux::RetentionAction::Delete => {
+                collection.delete_many(filter, Option::None).map(|_| ())
+            }
+            // The checksum of each item covers its state, so the items are archived one by one
+            model::aux::RetentionAction::Archive => {
+                let now: String = time::to_rfc3339(&Utc::now());
+                let mut archived: mongodb::Result<()> = Result::Ok(());
+                for mut feed_item in found {
+                    if let Some(e) = feed_item.transition(model::aux::ItemState::Archived) {
+                        return Result::Err(e);
+                    }
+                    let mut item_filter: Document = filter.clone();
+                    item_filter.insert("uuid", format!("{}", feed_item.get_uuid().unwrap()));
+                    let update: Document = doc! {
+                        "$set": {
+                            "state": format!("{}", feed_item.get_state()),
+                            "updated_at": now.clone(),
+                            "checksum": feed_item.get_checksum().unwrap_or_default()
+                        }
+                    };
+                    archived = collection
+                        .update_one(item_filter, update, Option::None)
+                        .map(|_| ());
+                    if archived.is_err() {
+                        break;
+                    }
+                }
+                archived
+            }
+        };
+        if let Err(e) = result {
+            warn!("failed to prune the feed items: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                &e,
+            ));
+        }
+
+        refresh_feed_checksum(self, parent_feed)?;
+        Result::Ok(pruned)
+    }
+}
+
+/// Get the reason a feed was not matched by a modification
+/// A conditional modification is not applied either if the feed does not exist or if its checksum
+/// changed
+fn feed_not_matched(
+    db_conn: std::sync::Arc<mongodb::db::DatabaseInner>,
+    uuid: Uuid,
+    conditional: bool,
+) -> Error {
+    if !conditional {
+        warn!("the database returned no feed");
+        return create_error!(SCOPE, FeedDbError::NoFeedFound);
+    }
+
+    match db_conn.get_feed(uuid) {
+        Ok(_) => {
+            warn!("the checksum of the feed does not match the expected one");
+            create_error!(SCOPE, FeedDbError::ChecksumMismatch)
+        }
+        Err(e) => e,
+    }
+}
+
+/// Get the reason a feed item was not matched by a modification
+fn item_not_matched(
+    db_conn: std::sync::Arc<mongodb::db::DatabaseInner>,
+    parent_feed: model::Feed,
+    uuid: Uuid,
+    conditional: bool,
+) -> Error {
+    if !conditional {
+        warn!("parent feed does not have such item");
+        return create_error!(SCOPE, FeedItemDbError::NoItemFound);
+    }
+
+    match db_conn.get_feed_item(parent_feed, uuid) {
+        Ok(_) => {
+            warn!("the checksum of the feed item does not match the expected one");
+            create_error!(SCOPE, FeedItemDbError::ChecksumMismatch)
+        }
+        Err(e) => e,
+    }
+}
+
+/// Find out why a state transition did not match any feed item: the item changed state in the
+/// meantime, or else the same reasons as any other modification
+fn state_not_matched(
+    db_conn: std::sync::Arc<mongodb::db::DatabaseInner>,
+    parent_feed: model::Feed,
+    uuid: Uuid,
+    previous_state: model::aux::ItemState,
+    conditional: bool,
+) -> Error {
+    match db_conn.clone().get_feed_item(parent_feed.clone(), uuid) {
+        Ok(value) if value.get_state() != previous_state => {
+            warn!("the state of the feed item changed in the meantime");
+            create_error!(SCOPE, ItemStateError::StateChanged)
+        }
+        _ => item_not_matched(db_conn, parent_feed, uuid, conditional),
+    }
+}
+
+/// Recompute the checksum of a feed after its items changed
+/// Only the checksum and the update time are written, so that concurrent changes to the feed are
+/// not overwritten
+fn refresh_feed_checksum(
+    db_conn: std::sync::Arc<mongodb::db::DatabaseInner>,
+    mut feed: model::Feed,
+) -> DbResult<model::Feed> {
+    // The update time is taken before the items are read, so that no item goes live between the
+    // two without being noticed by publish_due_items
+    feed.items = Option::None;
+    feed.set_updated_at(Utc::now());
+    if let Some(e) = feed.compute_checksum_with_items(db_conn.clone()) {
+        return Result::Err(e);
+    }
+
+    let filter: Document = doc! {
+        "uuid": format!("{}", feed.get_uuid().unwrap())
+    };
+    let update: Document = doc! {
+        "$set": {
+            "checksum": feed.get_checksum().unwrap_or_default(),
+            "updated_at": time::to_rfc3339(&feed.get_updated_at().unwrap())
+        }
+    };
+
+    match db_conn
+        .collection(model::Feed::COLLECTION_NAME)
+        .update_one(filter, update, Option::None)
+    {
+        Ok(_) => Result::Ok(feed),
+        Err(e) => {
+            warn!("failed to update the checksum of the feed: {:?}", e);
+            Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+                &e,
+            ))
+        }
+    }
+}
+
+/// Restrict a filter of feed items to the published ones
+/// Items stored without a state or a publication time are published, and scheduled items are
+/// published once their publication time passed
+fn filter_published(filter: &mut Document, include_scheduled: bool) {
+    filter.insert(
+        "state",
+        doc! {"$nin": [
+            format!("{}", model::aux::ItemState::Draft),
+            format!("{}", model::aux::ItemState::Archived)
+        ]},
+    );
+    if !include_scheduled {
+        filter.insert(
+            "published_at",
+            doc! {"$not": {"$gt": time::to_rfc3339(&Utc::now())}},
+        );
+    }
+}
+
+/// Get the alternative filters that match the feed items listed after a position, in an order
+/// Items without a publication time(null or missing) come first in chronological order
+fn after_cursor(cursor: ItemCursor, order: ItemOrder) -> Vec<mongodb::Bson> {
+    let operator: &str = match order {
+        ItemOrder::NewestFirst => "$lt",
+        ItemOrder::OldestFirst => "$gt",
+    };
+    let mut uuid: Document = Document::new();
+    uuid.insert(operator, format!("{}", cursor.uuid));
+
+    let mut alternatives: Vec<Document> = Vec::new();
+    match cursor.published_at {
+        Some(value) => {
+            let mut published_at: Document = Document::new();
+            published_at.insert(operator, time::to_rfc3339(&value));
+            alternatives.push(doc! {"published_at": published_at});
+            alternatives.push(doc! {"published_at": time::to_rfc3339(&value), "uuid": uuid});
+            if order == ItemOrder::NewestFirst {
+                alternatives.push(doc! {"published_at": mongodb::Bson::Null});
+            }
+        }
+        None => {
+            alternatives.push(doc! {"published_at": mongodb::Bson::Null, "uuid": uuid});
+            if order == ItemOrder::OldestFirst {
+                alternatives.push(doc! {"published_at": {"$ne": mongodb::Bson::Null}});
+            }
+        }
+    }
+    alternatives
+        .into_iter()
+        .map(mongodb::Bson::Document)
+        .collect()
+}
+
+/// Refresh the checksum of a feed if some of its scheduled items went live since it was updated
+/// Scheduled items are not covered by the checksum of the feed, so it changes the first time the
+/// feed is read after one of them is published
+fn publish_due_items(
+    db_conn: std::sync::Arc<mongodb::db::DatabaseInner>,
+    feed: model::Feed,
+) -> DbResult<model::Feed> {
+    let mut published_at: Document = doc! {
+        "$lte": time::to_rfc3339(&Utc::now())
+    };
+    if let Some(updated_at) = feed.get_updated_at() {
+        published_at.insert("$gt", time::to_rfc3339(&updated_at));
+    }
+    let filter: Document = doc! {
+        "feed_uuid": format!("{}", feed.get_uuid().unwrap()),
+        "published_at": published_at
+    };
+
+    match model::FeedItem::find_one(db_conn.clone(), Option::Some(filter), Option::None) {
+        Ok(Some(_)) => {
+            info!(
+                "publishing the scheduled items of feed {:?}",
+                feed.get_uuid()
+            );
+            refresh_feed_checksum(db_conn, feed)
+        }
+        Ok(None) => Result::Ok(feed),
+        Err(e) => {
+            warn!("failed to get the scheduled items of the feed: {:?}", e);
+            Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                &e,
+            ))
+        }
+    }
+}
+
+/// Get the fields of an update, without the ones kept by the database: the creation time and the
+/// state of items, which only changes through transitions
+fn without_kept_fields(fields: &Document) -> Document {
+    let mut update: Document = fields.clone();
+    update.remove("created_at");
+    update.remove("state");
+    update
+}
+
+/// Get the update of a stored document out of the fields of its model
+/// The optional fields the model does not have are removed, so that clearing them is persisted
+fn update_of(fields: &Document, optional_fields: &[&str]) -> Document {
+    let mut unset: Document = Document::new();
+    for field in optional_fields {
+        if !fields.contains_key(field) {
+            unset.insert(*field, "");
+        }
+    }
+
+    let mut update: Document = doc! {"$set": without_kept_fields(fields)};
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    update
+}
+
+/// Attach a driver error to a feeder error, along with the kind of failure it represents
+fn caused_by(error: Error, e: &mongodb::Error) -> Error {
+    let kind: BackendErrorKind = match e {
+        mongodb::Error::IoError(io_error) => match io_error.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => BackendErrorKind::Timeout,
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected => BackendErrorKind::ConnectionRefused,
+            _ => BackendErrorKind::Other,
+        },
+        // unique index violations are reported by the server with the E11000 code
+        _ if format!("{}", e).contains("E11000") => BackendErrorKind::DuplicateKey,
+        _ => BackendErrorKind::Other,
+    };
+
+    error.caused_by(kind, e)
+}
+
+/// Move the items of the feeds stored in the legacy format(a list of item uuids inside each
+/// feed) to the current format(each item references its feed)
+pub fn migrate(db_conn: std::sync::Arc<mongodb::db::DatabaseInner>) -> DbResult<Report<String>> {
+    if let Err(e) = model::FeedItem::sync(db_conn.clone()) {
+        warn!("failed to sync the feed item indexes: {:?}", e);
+        return Result::Err(caused_by(
+            create_error!(SCOPE, FeedDbError::FailedToMigrate),
+            &e,
+        ));
+    }
+
+    let filter: Document = doc! {
+        "items.uuid": {"$exists": true}
+    };
+    let legacy_feeds: Vec<model::Feed>;
+    match model::Feed::find(db_conn.clone(), Option::Some(filter), Option::None) {
+        Ok(value) => legacy_feeds = value,
+        Err(e) => {
+            warn!("failed to get the feeds that should be migrated: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToMigrate),
+                &e,
+            ));
+        }
+    }
+
+    let feeds_count: usize = legacy_feeds.len();
+    for feed in legacy_feeds {
+        let feed_uuid: String = format!("{}", feed.get_uuid().unwrap());
+        info!("migrating the items of feed {}", feed_uuid);
+
+        if let Some(model::ItemsVec::Uuid(item_uuids)) = feed.items {
+            let uuid_strings: Vec<mongodb::Bson> = item_uuids
+                .iter()
+                .map(|item_uuid| mongodb::Bson::String(format!("{}", item_uuid)))
+                .collect();
+            let items_filter: Document = doc! {
+                "uuid": {"$in": uuid_strings}
+            };
+            let items_update: Document = doc! {
+                "$set": {"feed_uuid": feed_uuid.clone()}
+            };
+            if let Err(e) = db_conn
+                .collection(model::FeedItem::COLLECTION_NAME)
+                .update_many(items_filter, items_update, Option::None)
+            {
+                warn!("failed to migrate the items of feed {}: {:?}", feed_uuid, e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToMigrate),
+                    &e,
+                ));
+            }
+        }
+
+        let feed_filter: Document = doc! {
+            "uuid": feed_uuid.clone()
+        };
+        let feed_update: Document = doc! {
+            "$unset": {"items": ""}
+        };
+        if let Err(e) = db_conn.collection(model::Feed::COLLECTION_NAME).update_one(
+            feed_filter,
+            feed_update,
+            Option::None,
+        ) {
+            warn!("failed to migrate feed {}: {:?}", feed_uuid, e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToMigrate),
+                &e,
+            ));
+        }
+    }
+
+    Result::Ok(Report::new(
+        SCOPE.to_string(),
+        format!("migrated {} feeds", feeds_count),
+    ))
+}
+
+/// Mongodb storage, backed by a pool of connections to the database
+pub struct MongoStore(Pool<MongodbConnectionManager>);
+
+impl MongoStore {
+    /// Create the pool of connections to the feeder database
+    pub fn from_config(config: &Config) -> Option<Self> {
+        storage::pool::<mongodb::db::Database>(config).map(MongoStore)
+    }
+}
+
+impl_storage!(
+    MongoStore,
+    |store| (*storage::connection(&store.0)?).clone(),
+    |store| migrate((*storage::connection(&store.0)?).clone())
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    use mongodb::{Client, ThreadedClient};
+
+    /// Connect to the database used by the tests, which is launched locally(see the Justfile)
+    fn connect() -> std::sync::Arc<mongodb::db::DatabaseInner> {
+        let url: String = env::var("FEEDER_TEST_MONGO_URL")
+            .unwrap_or_else(|_| String::from("mongodb://localhost:27017"));
+        let client: Client = Client::with_uri(url.as_str()).unwrap();
+        let db_conn = client.db("feeder_test");
+        migrate(db_conn.clone()).unwrap();
+        db_conn
+    }
+
+    #[test]
+    fn update_of_test() {
+        let fields: Document = doc! {
+            "title": "Title",
+            "category": "news",
+            "created_at": "2020-01-02T03:04:05.000Z",
+            "state": "published"
+        };
+
+        let update: Document = update_of(&fields, FEED_OPTIONAL_FIELDS);
+        assert_eq!(
+            update,
+            doc! {
+                "$set": {"title": "Title", "category": "news"},
+                "$unset": {"copyright": "", "image": "", "language": "", "retention": ""}
+            }
+        );
+
+        let fields: Document = doc! {
+            "author": "Author",
+            "comments": "https://example.com/comments",
+            "enclosure": {"url": "https://example.com/item.mp3"},
+            "published_at": "2020-01-02T03:04:05.000Z"
+        };
+        assert!(!update_of(&fields, ITEM_OPTIONAL_FIELDS).contains_key("$unset"));
+
+        // The publication time is never removed
+        let fields: Document = doc! {"title": "Title"};
+        assert_eq!(
+            update_of(&fields, ITEM_OPTIONAL_FIELDS),
+            doc! {
+                "$set": {"title": "Title"},
+                "$unset": {"author": "", "comments": "", "enclosure": ""}
+            }
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn clear_optional_fields_test() {
+        let db_conn = connect();
+        let mut feed: model::Feed =
+            model::Feed::new("Title", "Description", "https://example.com").unwrap();
+        feed.category = Option::Some("news".to_string());
+        feed.language = Option::Some("en".to_string());
+        let feed: model::Feed = db_conn.clone().create_feed(feed).unwrap();
+        let feed_uuid: Uuid = feed.get_uuid().unwrap();
+
+        let mut feed_item: model::FeedItem =
+            model::FeedItem::new("Title", "https://example.com/item", "Description").unwrap();
+        feed_item.author = Option::Some("Author".to_string());
+        let feed_item: model::FeedItem = db_conn
+            .clone()
+            .create_feed_item(feed.clone(), feed_item)
+            .unwrap();
+        let item_uuid: Uuid = feed_item.get_uuid().unwrap();
+
+        // Cleared fields are read back as cleared, and the stored checksums cover them
+        let mut cleared_feed: model::Feed = db_conn.clone().get_feed(feed_uuid).unwrap();
+        cleared_feed.category = Option::None;
+        let updated_feed: model::Feed = db_conn
+            .clone()
+            .update_feed(feed_uuid, cleared_feed, Option::None)
+            .unwrap();
+        let read_feed: model::Feed = db_conn.clone().get_feed(feed_uuid).unwrap();
+        assert_eq!(read_feed.category, Option::None);
+        assert_eq!(read_feed.language, Option::Some("en".to_string()));
+
+        let mut cleared_item: model::FeedItem = feed_item.clone();
+        cleared_item.author = Option::None;
+        let updated_item: model::FeedItem = db_conn
+            .clone()
+            .update_feed_item(updated_feed.clone(), item_uuid, cleared_item, Option::None)
+            .unwrap();
+        let read_item: model::FeedItem = db_conn
+            .clone()
+            .get_feed_item(updated_feed, item_uuid)
+            .unwrap();
+        assert_eq!(read_item.author, Option::None);
+        assert_eq!(read_item.get_checksum(), updated_item.get_checksum());
+
+        db_conn.delete_feed(feed_uuid, Option::None).unwrap();
+    }
 }
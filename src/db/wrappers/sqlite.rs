@@ -0,0 +1,1315 @@
+use crate::{
+    common::{
+        errors::{BackendErrorKind, Error, FeedDbError, FeedItemDbError},
+        report::Report,
+        time, DbResult,
+    },
+    create_error, impl_storage,
+};
+
+use crate::db::{
+    query::{FeedQuery, FeedSort, ItemOrder, ItemQuery, Page},
+    *,
+};
+
+use std::collections::HashSet;
+
+use chrono::Utc;
+use log::*;
+use rocket::Config;
+use rocket_contrib::databases::{r2d2::Pool, r2d2_sqlite::SqliteConnectionManager};
+use rusqlite::{types::ToSql, Connection};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+const SCOPE: &str = "database/sqlite";
+
+/// The migrations of the schema, the version of the schema is the number of applied migrations
+/// Models are stored as json documents, along with the columns used for querying them
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE feeds (
+        uuid TEXT PRIMARY KEY NOT NULL,
+        title TEXT,
+        category TEXT,
+        language TEXT,
+        checksum TEXT,
+        created_at TEXT,
+        updated_at TEXT,
+        document TEXT NOT NULL
+    );
+    CREATE INDEX feeds_title ON feeds (title);
+    CREATE TABLE feed_items (
+        uuid TEXT PRIMARY KEY NOT NULL,
+        feed_uuid TEXT NOT NULL,
+        state TEXT NOT NULL,
+        published_at TEXT,
+        checksum TEXT,
+        created_at TEXT,
+        updated_at TEXT,
+        document TEXT NOT NULL
+    );
+    CREATE INDEX feed_items_feed_uuid ON feed_items (feed_uuid, published_at);
+"];
+
+/// The columns that hold the fields managed by feeder, they take precedence over the document
+const FEED_FIELDS: &[&str] = &["uuid", "checksum", "created_at", "updated_at"];
+const ITEM_FIELDS: &[&str] = &[
+    "uuid",
+    "feed_uuid",
+    "state",
+    "published_at",
+    "checksum",
+    "created_at",
+    "updated_at",
+];
+
+/// Implementation of the FeederWrapper for Sqlite
+impl<'a> FeedWrapper for &'a Connection {
+    fn create_feed(self, feed: model::Feed) -> DbResult<model::Feed> {
+        let mut created_feed: model::Feed = model::Feed::new_from_model(feed)?;
+        created_feed.set_created_at(Utc::now());
+
+        let document: String = encode(
+            &created_feed,
+            create_error!(SCOPE, FeedDbError::FailedToSaveFeed),
+        )?;
+        match self.execute(
+            "INSERT INTO feeds (uuid, title, category, language, checksum, created_at, updated_at,
+                document)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            &[
+                &format!("{}", created_feed.get_uuid().unwrap()),
+                &created_feed.title,
+                &created_feed.category,
+                &created_feed.language,
+                &created_feed.get_checksum(),
+                &created_feed
+                    .get_created_at()
+                    .map(|value| time::to_rfc3339(&value)),
+                &created_feed
+                    .get_updated_at()
+                    .map(|value| time::to_rfc3339(&value)),
+                &document,
+            ],
+        ) {
+            Ok(_) => Result::Ok(created_feed),
+            Err(e) => {
+                warn!("error while saving feed: {:?}", e);
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToSaveFeed),
+                    &e,
+                ))
+            }
+        }
+    }
+
+    fn get_feed(self, uuid: Uuid) -> DbResult<model::Feed> {
+        let mut feeds: Vec<model::Feed> =
+            find_feeds(self, "WHERE uuid = ?", &[&format!("{}", uuid)])?;
+
+        match feeds.pop() {
+            Some(feed) => publish_due_items(self, feed),
+            None => {
+                warn!("the database returned no feed");
+                Result::Err(create_error!(SCOPE, FeedDbError::NoFeedFound))
+            }
+        }
+    }
+
+    fn get_feeds(self) -> DbResult<Vec<model::Feed>> {
+        find_feeds(self, "ORDER BY rowid", &[])
+    }
+
+    fn list_feeds(self, query: FeedQuery) -> DbResult<Page<model::Feed>> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut params: Vec<String> = Vec::new();
+        if let Some(category) = query.category {
+            conditions.push("category = ?");
+            params.push(category);
+        }
+        if let Some(language) = query.language {
+            conditions.push("language = ?");
+            params.push(language);
+        }
+
+        // The rowid follows the order the feeds were created in, so it is used for sorting by
+        // creation time and as a tie breaker for sorting by title
+        let sort: &str = match query.sort {
+            FeedSort::TitleAscending => "title ASC, rowid ASC",
+            FeedSort::TitleDescending => "title DESC, rowid DESC",
+            FeedSort::CreatedAscending => "rowid ASC",
+            FeedSort::CreatedDescending => "rowid DESC",
+        };
+
+        // Fetch one more feed than requested in order to know if there is a next page
+        let condition: String = format!(
+            "{} ORDER BY {} LIMIT {} OFFSET {}",
+            where_clause(&conditions),
+            sort,
+            i64::from(query.pagination.limit) + 1,
+            query.pagination.offset
+        );
+        let param_refs: Vec<&dyn ToSql> = params.iter().map(|value| value as &dyn ToSql).collect();
+
+        Result::Ok(Page::from_results(
+            find_feeds(self, &condition, &param_refs)?,
+            query.pagination.offset,
+            query.pagination.limit,
+        ))
+    }
+
+    fn update_feed(
+        self,
+        uuid: Uuid,
+        mut feed: model::Feed,
+        checksum: Option<String>,
+    ) -> DbResult<model::Feed> {
+        // The uuid may only be in the url, the items of the feed are looked up with it
+        feed.set_uuid(uuid);
+        // Items are stored separately and reference their feed
+        feed.items = Option::None;
+        feed.set_updated_at(Utc::now());
+
+        if let Some(e) = feed.compute_checksum_with_items(self) {
+            return Result::Err(e);
+        }
+
+        let document: String =
+            encode(&feed, create_error!(SCOPE, FeedDbError::FailedToUpdateFeed))?;
+
+        // Compare and set: nothing is updated if the feed changed in the meantime
+        // The creation time is not updated, so the database keeps it
+        let conditional: bool = checksum.is_some();
+        match self.execute(
+            "UPDATE feeds
+            SET title = ?1, category = ?2, language = ?3, checksum = ?4, updated_at = ?5,
+                document = ?6
+            WHERE uuid = ?7 AND (?8 IS NULL OR checksum = ?8)",
+            &[
+                &feed.title,
+                &feed.category,
+                &feed.language,
+                &feed.get_checksum(),
+                &feed.get_updated_at().map(|value| time::to_rfc3339(&value)),
+                &document,
+                &format!("{}", uuid),
+                &checksum,
+            ],
+        ) {
+            Ok(0) => Result::Err(feed_not_matched(self, uuid, conditional)),
+            Ok(_) => self.get_feed(uuid),
+            Err(e) => {
+                warn!("error updating the feed: {:?}", e);
+                Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+                    &e,
+                ))
+            }
+        }
+    }
+
+    fn delete_feed(self, _uuid: Uuid, checksum: Option<String>) -> DbResult<Report<String>> {
+        // The feed and its items are deleted together
+        let transaction: Transaction =
+            begin(self, create_error!(SCOPE, FeedDbError::FailedToDeleteFeed))?;
+
+        // Compare and delete: nothing is deleted if the feed changed in the meantime
+        let conditional: bool = checksum.is_some();
+        match self.execute(
+            "DELETE FROM feeds WHERE uuid = ?1 AND (?2 IS NULL OR checksum = ?2)",
+            &[&format!("{}", _uuid), &checksum],
+        ) {
+            Ok(0) => return Result::Err(feed_not_matched(self, _uuid, conditional)),
+            Ok(_) => {}
+            Err(e) => {
+                warn!("failed to delete the feed: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedDbError::FailedToDeleteFeed),
+                    &e,
+                ));
+            }
+        }
+
+        // Delete the items of the feed as well
+        if let Err(e) = self.execute(
+            "DELETE FROM feed_items WHERE feed_uuid = ?1",
+            &[&format!("{}", _uuid)],
+        ) {
+            warn!("failed to delete the items of the deleted feed: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToDeleteFeed),
+                &e,
+            ));
+        }
+
+        commit(
+            transaction,
+            create_error!(SCOPE, FeedDbError::FailedToDeleteFeed),
+        )?;
+
+        Result::Ok(Report::new(SCOPE.to_string(), "deleted feed".to_string()))
+    }
+
+    fn get_feed_checksum(self, _uuid: Uuid) -> DbResult<String> {
+        let feed: model::Feed = self.get_feed(_uuid)?;
+        if let Some(value) = feed.get_checksum() {
+            return Result::Ok(value);
+        }
+        warn!("the feed has no checksum");
+        Result::Err(create_error!(SCOPE, FeedDbError::FeedHasNoChecksum))
+    }
+}
+
+/// Implementation of FeedItemWrapper for Sqlite
+impl<'a> FeedItemWrapper for &'a Connection {
+    fn create_feed_item(
+        self,
+        parent_feed: model::Feed,
+        feed_item: model::FeedItem,
+    ) -> DbResult<model::FeedItem> {
+        let mut created_feed_item = model::FeedItem::new_from_model(feed_item)?;
+        created_feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        created_feed_item.set_created_at(Utc::now());
+
+        // The item and the checksum of its feed are saved together
+        let transaction: Transaction = begin(
+            self,
+            create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+        )?;
+        let document: String = encode(
+            &created_feed_item,
+            create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+        )?;
+        if let Err(e) = self.execute(
+            "INSERT INTO feed_items (uuid, feed_uuid, state, published_at, checksum, created_at,
+                updated_at, document)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            &[
+                &format!("{}", created_feed_item.get_uuid().unwrap()),
+                &format!("{}", parent_feed.get_uuid().unwrap()),
+                &format!("{}", created_feed_item.get_state()),
+                &created_feed_item
+                    .published_at
+                    .map(|value| time::to_rfc3339(&value)),
+                &created_feed_item.get_checksum(),
+                &created_feed_item
+                    .get_created_at()
+                    .map(|value| time::to_rfc3339(&value)),
+                &created_feed_item
+                    .get_updated_at()
+                    .map(|value| time::to_rfc3339(&value)),
+                &document,
+            ],
+        ) {
+            warn!("failed to save feed item in the database: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+                &e,
+            ));
+        }
+
+        refresh_feed_checksum(self, parent_feed)?;
+        commit(
+            transaction,
+            create_error!(SCOPE, FeedItemDbError::FailedToSaveItem),
+        )?;
+
+        Result::Ok(created_feed_item)
+    }
+
+    fn get_feed_item(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<model::FeedItem> {
+        let mut items: Vec<model::FeedItem> = find_items(
+            self,
+            "WHERE uuid = ? AND feed_uuid = ?",
+            &[
+                &format!("{}", uuid),
+                &format!("{}", parent_feed.get_uuid().unwrap()),
+            ],
+        )?;
+
+        match items.pop() {
+            Some(feed_item) => Result::Ok(feed_item),
+            None => {
+                warn!("parent feed has no matching feed item");
+                Result::Err(create_error!(SCOPE, FeedItemDbError::NoItemFound))
+            }
+        }
+    }
+
+    fn get_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Option<Vec<Uuid>>,
+        include_unpublished: bool,
+    ) -> DbResult<ItemsLookup> {
+        // If no uuids are passed to the function, search for all feed items in this feed
+        // Otherwise, search only for the specified ones
+        let uuid_condition: String;
+        let mut conditions: Vec<&str> = vec!["feed_uuid = ?"];
+        let mut params: Vec<String> = vec![format!("{}", parent_feed.get_uuid().unwrap())];
+        if let Some(uuid_vec) = &uuids {
+            // If there are no items, return an empty vector
+            if uuid_vec.is_empty() {
+                return Result::Ok(ItemsLookup {
+                    items: Vec::new(),
+                    missing: Vec::new(),
+                });
+            }
+
+            uuid_condition = format!("uuid IN ({})", vec!["?"; uuid_vec.len()].join(", "));
+            conditions.push(&uuid_condition);
+            for item_uuid in uuid_vec {
+                params.push(format!("{}", item_uuid));
+            }
+        }
+        if !include_unpublished {
+            filter_published(&mut conditions, &mut params, false);
+        }
+
+        // Items are kept in the order they were created in
+        let condition: String = format!("{} ORDER BY rowid", where_clause(&conditions));
+        let param_refs: Vec<&dyn ToSql> = params.iter().map(|value| value as &dyn ToSql).collect();
+        let items_vec: Vec<model::FeedItem> = find_items(self, &condition, &param_refs)?;
+
+        // The requested uuids that were not found are missing
+        let mut missing: Vec<Uuid> = Vec::new();
+        if let Some(uuid_vec) = uuids {
+            let found: HashSet<Uuid> = items_vec
+                .iter()
+                .filter_map(|feed_item| feed_item.get_uuid())
+                .collect();
+            for item_uuid in uuid_vec {
+                if !found.contains(&item_uuid) && !missing.contains(&item_uuid) {
+                    missing.push(item_uuid);
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            warn!("the database returned no feed items for {:?}", missing);
+        }
+
+        Result::Ok(ItemsLookup {
+            items: items_vec,
+            missing,
+        })
+    }
+
+    fn list_feed_items(
+        self,
+        parent_feed: model::Feed,
+        query: ItemQuery,
+    ) -> DbResult<Page<model::FeedItem>> {
+        // Items are ordered by their publication time and then by uuid, items without a
+        // publication time come first in chronological order
+        let (sort, comparison): (&str, &str) = match query.order {
+            ItemOrder::NewestFirst => ("published_at DESC, uuid DESC", "<"),
+            ItemOrder::OldestFirst => ("published_at ASC, uuid ASC", ">"),
+        };
+        let keyset: String = format!(
+            "(COALESCE(published_at, '') {0} ? OR (COALESCE(published_at, '') = ? AND uuid {0} ?))",
+            comparison
+        );
+
+        let mut conditions: Vec<&str> = vec!["feed_uuid = ?"];
+        let mut params: Vec<String> = vec![format!("{}", parent_feed.get_uuid().unwrap())];
+        match query.state {
+            model::aux::ItemState::Published => {
+                filter_published(&mut conditions, &mut params, query.include_scheduled)
+            }
+            state => {
+                conditions.push("state = ?");
+                params.push(format!("{}", state));
+            }
+        }
+
+        // The page starts right after the last item of the previous page
+        if let Some(cursor) = query.pagination.after {
+            let published_at: String = cursor
+                .published_at
+                .map(|value| time::to_rfc3339(&value))
+                .unwrap_or_default();
+            conditions.push(&keyset);
+            params.push(published_at.clone());
+            params.push(published_at);
+            params.push(format!("{}", cursor.uuid));
+        }
+
+        // Fetch one more item than requested in order to know if there is a next page
+        let condition: String = format!(
+            "{} ORDER BY {} LIMIT {}",
+            where_clause(&conditions),
+            sort,
+            i64::from(query.pagination.limit) + 1
+        );
+        let param_refs: Vec<&dyn ToSql> = params.iter().map(|value| value as &dyn ToSql).collect();
+
+        Result::Ok(Page::from_items(
+            find_items(self, &condition, &param_refs)?,
+            query.pagination.limit,
+        ))
+    }
+
+    fn update_feed_item(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        mut feed_item: model::FeedItem,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        // The item must stay in its feed
+        feed_item.set_feed_uuid(parent_feed.get_uuid().unwrap());
+        feed_item.set_updated_at(Utc::now());
+
+        // The fields managed by feeder are kept from the stored item
+        let stored: model::FeedItem = self.get_feed_item(parent_feed.clone(), uuid)?;
+        feed_item.keep_managed_fields(&stored);
+
+        // Compute the new checksum
+        if let Some(e) = feed_item.compute_checksum() {
+            warn!("failed to compute the checksum for the feed item");
+            return Result::Err(e);
+        }
+
+        let document: String = encode(
+            &feed_item,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+
+        // The item and the checksum of its feed are saved together
+        let transaction: Transaction =
+            begin(self, create_error!(SCOPE, FeedItemDbError::FailedToUpdate))?;
+
+        // Compare and set: nothing is updated if the item changed in the meantime
+        // The creation time and the state are not updated, so the database keeps them
+        let conditional: bool = checksum.is_some();
+        match self.execute(
+            "UPDATE feed_items
+            SET published_at = ?1, checksum = ?2, updated_at = ?3, document = ?4
+            WHERE uuid = ?5 AND feed_uuid = ?6 AND (?7 IS NULL OR checksum = ?7)",
+            &[
+                &feed_item.published_at.map(|value| time::to_rfc3339(&value)),
+                &feed_item.get_checksum(),
+                &feed_item
+                    .get_updated_at()
+                    .map(|value| time::to_rfc3339(&value)),
+                &document,
+                &format!("{}", uuid),
+                &format!("{}", parent_feed.get_uuid().unwrap()),
+                &checksum,
+            ],
+        ) {
+            Ok(0) => {
+                return Result::Err(item_not_matched(self, parent_feed, uuid, conditional));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("error updating the feed item: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+                    &e,
+                ));
+            }
+        }
+
+        let updated_feed_item: model::FeedItem = self.get_feed_item(parent_feed.clone(), uuid)?;
+        refresh_feed_checksum(self, parent_feed)?;
+        commit(
+            transaction,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+        Result::Ok(updated_feed_item)
+    }
+
+    /// Delete a feed item
+    fn delete_feed_item(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        checksum: Option<String>,
+    ) -> DbResult<Report<String>> {
+        // The item is deleted along with the change to the checksum of its feed
+        let transaction: Transaction =
+            begin(self, create_error!(SCOPE, FeedItemDbError::FailedToDelete))?;
+
+        // Compare and delete: nothing is deleted if the item changed in the meantime
+        let conditional: bool = checksum.is_some();
+        match self.execute(
+            "DELETE FROM feed_items
+            WHERE uuid = ?1 AND feed_uuid = ?2 AND (?3 IS NULL OR checksum = ?3)",
+            &[
+                &format!("{}", uuid),
+                &format!("{}", parent_feed.get_uuid().unwrap()),
+                &checksum,
+            ],
+        ) {
+            Ok(0) => {
+                return Result::Err(item_not_matched(self, parent_feed, uuid, conditional));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("failed to delete the feed item: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                    &e,
+                ));
+            }
+        }
+
+        refresh_feed_checksum(self, parent_feed)?;
+        commit(
+            transaction,
+            create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+        )?;
+
+        Result::Ok(Report::new(
+            SCOPE.to_string(),
+            "deleted feed item".to_string(),
+        ))
+    }
+
+    /// Move a feed item to another stage of its lifecycle
+    fn set_feed_item_state(
+        self,
+        parent_feed: model::Feed,
+        uuid: Uuid,
+        state: model::aux::ItemState,
+        checksum: Option<String>,
+    ) -> DbResult<model::FeedItem> {
+        let mut feed_item: model::FeedItem = self.get_feed_item(parent_feed.clone(), uuid)?;
+        if let Some(e) = feed_item.transition(state) {
+            return Result::Err(e);
+        }
+        feed_item.set_updated_at(Utc::now());
+
+        // The item and the checksum of its feed are saved together
+        let transaction: Transaction =
+            begin(self, create_error!(SCOPE, FeedItemDbError::FailedToUpdate))?;
+
+        // Publishing a draft can change its publication time, and the checksum covers the state
+        let conditional: bool = checksum.is_some();
+        match self.execute(
+            "UPDATE feed_items
+            SET state = ?1, published_at = COALESCE(?2, published_at), updated_at = ?3,
+                checksum = ?7
+            WHERE uuid = ?4 AND feed_uuid = ?5 AND (?6 IS NULL OR checksum = ?6)",
+            &[
+                &format!("{}", feed_item.get_state()),
+                &feed_item.published_at.map(|value| time::to_rfc3339(&value)),
+                &feed_item
+                    .get_updated_at()
+                    .map(|value| time::to_rfc3339(&value)),
+                &format!("{}", uuid),
+                &format!("{}", parent_feed.get_uuid().unwrap()),
+                &checksum,
+                &feed_item.get_checksum(),
+            ],
+        ) {
+            Ok(0) => {
+                return Result::Err(item_not_matched(self, parent_feed, uuid, conditional));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("failed to change the state of the feed item: {:?}", e);
+                return Result::Err(caused_by(
+                    create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+                    &e,
+                ));
+            }
+        }
+
+        refresh_feed_checksum(self, parent_feed)?;
+        commit(
+            transaction,
+            create_error!(SCOPE, FeedItemDbError::FailedToUpdate),
+        )?;
+        Result::Ok(feed_item)
+    }
+
+    /// Get the checksum of a feed item
+    fn get_feed_item_checksum(self, parent_feed: model::Feed, uuid: Uuid) -> DbResult<String> {
+        let feed_item: model::FeedItem;
+
+        // If the feed does not have such item, error
+        match self.get_feed_item(parent_feed, uuid) {
+            Ok(value) => feed_item = value,
+            Err(e) => {
+                warn!("parent feed does not have such item: {:?}", e);
+                return Result::Err(e);
+            }
+        }
+
+        if let Some(value) = feed_item.get_checksum() {
+            return Result::Ok(value);
+        }
+        Result::Err(create_error!(SCOPE, FeedItemDbError::NoChecksum))
+    }
+
+    /// Delete or archive some published feed items at once
+    fn prune_feed_items(
+        self,
+        parent_feed: model::Feed,
+        uuids: Vec<Uuid>,
+        action: model::aux::RetentionAction,
+    ) -> DbResult<Vec<Uuid>> {
+        if uuids.is_empty() {
+            return Result::Ok(Vec::new());
+        }
+
+        // The items and the checksum of their feed are saved together
+        let transaction: Transaction =
+            begin(self, create_error!(SCOPE, FeedItemDbError::FailedToDelete))?;
+
+        let placeholders: Vec<&str> = uuids.iter().map(|_| "?").collect();
+        let condition: String = format!(
+            "WHERE feed_uuid = ? AND state = ? AND uuid IN ({})",
+            placeholders.join(", ")
+        );
+        let mut params: Vec<String> = vec![
+            format!("{}", parent_feed.get_uuid().unwrap()),
+            format!("{}", model::aux::ItemState::Published),
+        ];
+        params.extend(uuids.iter().map(|item_uuid| format!("{}", item_uuid)));
+        let param_refs: Vec<&dyn ToSql> = params.iter().map(|value| value as &dyn ToSql).collect();
+
+        let found: Vec<model::FeedItem> = find_items(self, &condition, &param_refs)?;
+        let pruned: Vec<Uuid> = found
+            .iter()
+            .filter_map(|feed_item| feed_item.get_uuid())
+            .collect();
+        if pruned.is_empty() {
+            return Result::Ok(pruned);
+        }
+
+        let result: rusqlite::Result<usize> = match action {
+            model::aux::RetentionAction::Delete => self.execute(
+                &format!("DELETE FROM feed_items {}", condition),
+                &param_refs,
+            ),
+            // The checksum of an item covers its state, so each archived item is updated
+            model::aux::RetentionAction::Archive => {
+                let now: String = time::to_rfc3339(&Utc::now());
+                let mut result: rusqlite::Result<usize> = Result::Ok(0);
+                for mut feed_item in found {
+                    if let Some(e) = feed_item.transition(model::aux::ItemState::Archived) {
+                        return Result::Err(e);
+                    }
+                    result = self.execute(
+                        "UPDATE feed_items SET state = ?1, updated_at = ?2, checksum = ?3
+                        WHERE uuid = ?4",
+                        &[
+                            &format!("{}", model::aux::ItemState::Archived) as &dyn ToSql,
+                            &now,
+                            &feed_item.get_checksum(),
+                            &format!("{}", feed_item.get_uuid().unwrap()),
+                        ],
+                    );
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                result
+            }
+        };
+        if let Err(e) = result {
+            warn!("failed to prune the feed items: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+                &e,
+            ));
+        }
+
+        refresh_feed_checksum(self, parent_feed)?;
+        commit(
+            transaction,
+            create_error!(SCOPE, FeedItemDbError::FailedToDelete),
+        )?;
+        Result::Ok(pruned)
+    }
+}
+
+/// A stored document, along with the values of the fields managed by feeder
+struct StoredRow {
+    document: String,
+    fields: Vec<(&'static str, Option<String>)>,
+}
+
+/// Read a row selected along with the given fields
+fn read_row(row: &rusqlite::Row, fields: &'static [&'static str]) -> rusqlite::Result<StoredRow> {
+    let document: String = row.get_checked("document")?;
+    let mut values: Vec<(&'static str, Option<String>)> = Vec::new();
+    for field in fields {
+        values.push((*field, row.get_checked(*field)?));
+    }
+
+    Result::Ok(StoredRow {
+        document,
+        fields: values,
+    })
+}
+
+/// Read the rows of a table that match a condition(and the clauses that follow it)
+fn select_rows(
+    db_conn: &Connection,
+    table: &str,
+    fields: &'static [&'static str],
+    condition: &str,
+    params: &[&dyn ToSql],
+) -> rusqlite::Result<Vec<StoredRow>> {
+    let sql: String = format!(
+        "SELECT document, {} FROM {} {}",
+        fields.join(", "),
+        table,
+        condition
+    );
+    let mut statement = db_conn.prepare(&sql)?;
+    let rows = statement.query_map(params, |row| read_row(row, fields))?;
+
+    let mut stored: Vec<StoredRow> = Vec::new();
+    for row in rows {
+        stored.push(row??);
+    }
+    Result::Ok(stored)
+}
+
+/// Get the feeds that match a condition
+fn find_feeds(
+    db_conn: &Connection,
+    condition: &str,
+    params: &[&dyn ToSql],
+) -> DbResult<Vec<model::Feed>> {
+    match select_rows(db_conn, "feeds", FEED_FIELDS, condition, params) {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|row| decode(row, create_error!(SCOPE, FeedDbError::FailedToGetFeeds)))
+            .collect(),
+        Err(e) => {
+            warn!("failed to get the feeds: {:?}", e);
+            Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToGetFeeds),
+                &e,
+            ))
+        }
+    }
+}
+
+/// Get the feed items that match a condition
+fn find_items(
+    db_conn: &Connection,
+    condition: &str,
+    params: &[&dyn ToSql],
+) -> DbResult<Vec<model::FeedItem>> {
+    match select_rows(db_conn, "feed_items", ITEM_FIELDS, condition, params) {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|row| decode(row, create_error!(SCOPE, FeedItemDbError::FailedToGetItems)))
+            .collect(),
+        Err(e) => {
+            warn!("failed to get the feed items: {:?}", e);
+            Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                &e,
+            ))
+        }
+    }
+}
+
+/// Encode a model into the document stored along with its columns
+fn encode<T>(model: &T, error: Error) -> DbResult<String>
+where
+    T: Serialize,
+{
+    serde_json::to_string(model).map_err(|e| {
+        warn!("failed to encode the model into json: {:?}", e);
+        error.caused_by(BackendErrorKind::Other, &e)
+    })
+}
+
+/// Decode a stored document into a model, the columns of the fields managed by feeder take
+/// precedence over the document
+fn decode<T>(row: StoredRow, error: Error) -> DbResult<T>
+where
+    T: DeserializeOwned,
+{
+    let mut document: Value;
+    match serde_json::from_str(&row.document) {
+        Ok(value) => document = value,
+        Err(e) => {
+            warn!("failed to decode the stored document: {:?}", e);
+            return Result::Err(error.caused_by(BackendErrorKind::Other, &e));
+        }
+    }
+
+    if let Value::Object(fields) = &mut document {
+        for (name, value) in row.fields {
+            match value {
+                Some(_value) => fields.insert(name.to_string(), Value::String(_value)),
+                None => fields.remove(name),
+            };
+        }
+    }
+
+    serde_json::from_value(document).map_err(|e| {
+        warn!("failed to decode the stored document: {:?}", e);
+        error.caused_by(BackendErrorKind::Other, &e)
+    })
+}
+
+/// Join conditions into a where clause
+fn where_clause(conditions: &[&str]) -> String {
+    if conditions.is_empty() {
+        return String::new();
+    }
+    format!("WHERE {}", conditions.join(" AND "))
+}
+
+/// Restrict the conditions of a query of feed items to the published ones
+/// Items stored without a publication time are published, and scheduled items are published
+/// once their publication time passed
+fn filter_published(conditions: &mut Vec<&str>, params: &mut Vec<String>, include_scheduled: bool) {
+    conditions.push("state = ?");
+    params.push(format!("{}", model::aux::ItemState::Published));
+    if !include_scheduled {
+        conditions.push("(published_at IS NULL OR published_at <= ?)");
+        params.push(time::to_rfc3339(&Utc::now()));
+    }
+}
+
+/// Get the reason a feed was not matched by a modification
+/// A conditional modification is not applied either if the feed does not exist or if its checksum
+/// changed
+fn feed_not_matched(db_conn: &Connection, uuid: Uuid, conditional: bool) -> Error {
+    if !conditional {
+        warn!("the database returned no feed");
+        return create_error!(SCOPE, FeedDbError::NoFeedFound);
+    }
+
+    match db_conn.get_feed(uuid) {
+        Ok(_) => {
+            warn!("the checksum of the feed does not match the expected one");
+            create_error!(SCOPE, FeedDbError::ChecksumMismatch)
+        }
+        Err(e) => e,
+    }
+}
+
+/// Get the reason a feed item was not matched by a modification
+fn item_not_matched(
+    db_conn: &Connection,
+    parent_feed: model::Feed,
+    uuid: Uuid,
+    conditional: bool,
+) -> Error {
+    if !conditional {
+        warn!("parent feed does not have such item");
+        return create_error!(SCOPE, FeedItemDbError::NoItemFound);
+    }
+
+    match db_conn.get_feed_item(parent_feed, uuid) {
+        Ok(_) => {
+            warn!("the checksum of the feed item does not match the expected one");
+            create_error!(SCOPE, FeedItemDbError::ChecksumMismatch)
+        }
+        Err(e) => e,
+    }
+}
+
+/// Recompute the checksum of a feed after its items changed
+/// Only the checksum and the update time are written, so that concurrent changes to the feed are
+/// not overwritten
+fn refresh_feed_checksum(db_conn: &Connection, mut feed: model::Feed) -> DbResult<model::Feed> {
+    // The items are read and the checksum is written without other changes in between
+    let transaction: Transaction = begin(
+        db_conn,
+        create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+    )?;
+
+    // The update time is taken before the items are read, so that no item goes live between the
+    // two without being noticed by publish_due_items
+    feed.items = Option::None;
+    feed.set_updated_at(Utc::now());
+    if let Some(e) = feed.compute_checksum_with_items(db_conn) {
+        return Result::Err(e);
+    }
+
+    if let Err(e) = db_conn.execute(
+        "UPDATE feeds SET checksum = ?1, updated_at = ?2 WHERE uuid = ?3",
+        &[
+            &feed.get_checksum(),
+            &time::to_rfc3339(&feed.get_updated_at().unwrap()),
+            &format!("{}", feed.get_uuid().unwrap()),
+        ],
+    ) {
+        warn!("failed to update the checksum of the feed: {:?}", e);
+        return Result::Err(caused_by(
+            create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+            &e,
+        ));
+    }
+
+    commit(
+        transaction,
+        create_error!(SCOPE, FeedDbError::FailedToUpdateFeed),
+    )?;
+    Result::Ok(feed)
+}
+
+/// Refresh the checksum of a feed if some of its scheduled items went live since it was updated
+/// Scheduled items are not covered by the checksum of the feed, so it changes the first time the
+/// feed is read after one of them is published
+fn publish_due_items(db_conn: &Connection, feed: model::Feed) -> DbResult<model::Feed> {
+    let updated_at: String = feed
+        .get_updated_at()
+        .map(|value| time::to_rfc3339(&value))
+        .unwrap_or_default();
+
+    match db_conn.query_row(
+        "SELECT COUNT(*) AS due FROM feed_items
+        WHERE feed_uuid = ?1 AND published_at > ?2 AND published_at <= ?3",
+        &[
+            &format!("{}", feed.get_uuid().unwrap()),
+            &updated_at,
+            &time::to_rfc3339(&Utc::now()),
+        ],
+        |row| row.get_checked::<_, i64>("due"),
+    ) {
+        Ok(Ok(0)) => Result::Ok(feed),
+        Ok(Ok(_)) => {
+            info!(
+                "publishing the scheduled items of feed {:?}",
+                feed.get_uuid()
+            );
+            refresh_feed_checksum(db_conn, feed)
+        }
+        Ok(Err(e)) | Err(e) => {
+            warn!("failed to get the scheduled items of the feed: {:?}", e);
+            Result::Err(caused_by(
+                create_error!(SCOPE, FeedItemDbError::FailedToGetItems),
+                &e,
+            ))
+        }
+    }
+}
+
+/// A transaction on a connection, which is rolled back unless it is committed
+/// Connections are shared by the wrappers, so transactions are savepoints instead of the ones of
+/// Connection::transaction, which needs a mutable connection, and they can be nested
+struct Transaction<'a> {
+    db_conn: &'a Connection,
+    committed: bool,
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        if let Err(e) = self
+            .db_conn
+            .execute_batch("ROLLBACK TO feeder; RELEASE feeder;")
+        {
+            warn!("failed to roll back the transaction: {:?}", e);
+        }
+    }
+}
+
+/// Begin a transaction
+fn begin(db_conn: &Connection, error: Error) -> DbResult<Transaction> {
+    match db_conn.execute_batch("SAVEPOINT feeder;") {
+        Ok(_) => Result::Ok(Transaction {
+            db_conn,
+            committed: false,
+        }),
+        Err(e) => {
+            warn!("failed to begin the transaction: {:?}", e);
+            Result::Err(caused_by(error, &e))
+        }
+    }
+}
+
+/// Commit a transaction
+fn commit(mut transaction: Transaction, error: Error) -> DbResult<()> {
+    match transaction.db_conn.execute_batch("RELEASE feeder;") {
+        Ok(_) => {
+            transaction.committed = true;
+            Result::Ok(())
+        }
+        Err(e) => {
+            warn!("failed to commit the transaction: {:?}", e);
+            Result::Err(caused_by(error, &e))
+        }
+    }
+}
+
+/// Attach a driver error to a feeder error, along with the kind of failure it represents
+fn caused_by(error: Error, e: &rusqlite::Error) -> Error {
+    let kind: BackendErrorKind = match e {
+        rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+            // unique constraints are violated by uuids that are already stored
+            rusqlite::ErrorCode::ConstraintViolation => BackendErrorKind::DuplicateKey,
+            rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked => {
+                BackendErrorKind::Timeout
+            }
+            rusqlite::ErrorCode::CannotOpen => BackendErrorKind::ConnectionRefused,
+            _ => BackendErrorKind::Other,
+        },
+        _ => BackendErrorKind::Other,
+    };
+    error.caused_by(kind, e)
+}
+
+/// Apply the migrations of the schema that were not applied yet
+pub fn migrate(db_conn: &Connection) -> DbResult<Report<String>> {
+    let version: i64;
+    match db_conn.query_row("PRAGMA user_version", &[], |row| {
+        row.get_checked::<_, i64>("user_version")
+    }) {
+        Ok(Ok(value)) => version = value,
+        Ok(Err(e)) | Err(e) => {
+            warn!("failed to get the version of the schema: {:?}", e);
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToMigrate),
+                &e,
+            ));
+        }
+    }
+
+    // Each migration is applied in a transaction along with the new version
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        info!("migrating the schema to version {}", index + 1);
+        let batch: String = format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration,
+            index + 1
+        );
+        if let Err(e) = db_conn.execute_batch(&batch) {
+            warn!("failed to migrate the schema: {:?}", e);
+            if let Err(_e) = db_conn.execute_batch("ROLLBACK;") {
+                warn!("failed to roll back the migration: {:?}", _e);
+            }
+            return Result::Err(caused_by(
+                create_error!(SCOPE, FeedDbError::FailedToMigrate),
+                &e,
+            ));
+        }
+    }
+
+    Result::Ok(Report::new(
+        SCOPE.to_string(),
+        format!(
+            "migrated the schema from version {} to version {}",
+            version,
+            MIGRATIONS.len()
+        ),
+    ))
+}
+
+/// Sqlite storage, backed by a pool of connections to the database file
+pub struct SqliteStore(Pool<SqliteConnectionManager>);
+
+impl SqliteStore {
+    /// Create the pool of connections to the feeder database
+    pub fn from_config(config: &Config) -> Option<Self> {
+        storage::pool::<Connection>(config).map(SqliteStore)
+    }
+}
+
+impl_storage!(
+    SqliteStore,
+    |store| &*storage::connection(&store.0)?,
+    |store| migrate(&*storage::connection(&store.0)?)
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::db::model::aux::{ItemState, RetentionAction};
+
+    /// Open a database in memory, along with the current schema
+    fn connect() -> Connection {
+        let db_conn: Connection = Connection::open_in_memory().unwrap();
+        migrate(&db_conn).unwrap();
+        db_conn
+    }
+
+    /// Count the rows of a table
+    fn count(db_conn: &Connection, table: &str) -> i64 {
+        db_conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), &[], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn migrate_test() {
+        let db_conn: Connection = connect();
+
+        // Migrating again applies nothing
+        migrate(&db_conn).unwrap();
+        let version: i64 = db_conn
+            .query_row("PRAGMA user_version", &[], |row| row.get::<_, i64>(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn feeds_test() {
+        let db_conn: Connection = connect();
+        let mut feed: model::Feed = (&db_conn)
+            .create_feed(model::Feed::new("Title", "Description", "https://example.com").unwrap())
+            .unwrap();
+        let feed_uuid: Uuid = feed.get_uuid().unwrap();
+        assert_eq!(
+            (&db_conn).get_feed(feed_uuid).unwrap().get_checksum(),
+            feed.get_checksum()
+        );
+
+        // Stale checksums do not update anything
+        feed.category = Option::Some("news".to_string());
+        let result =
+            (&db_conn).update_feed(feed_uuid, feed.clone(), Option::Some("stale".to_string()));
+        assert_eq!(result.unwrap_err().code(), "checksum_mismatch");
+        let updated_feed: model::Feed = (&db_conn)
+            .update_feed(feed_uuid, feed.clone(), feed.get_checksum())
+            .unwrap();
+        assert_eq!(
+            (&db_conn).get_feed(feed_uuid).unwrap().category,
+            Option::Some("news".to_string())
+        );
+
+        (&db_conn)
+            .create_feed_item(
+                updated_feed,
+                model::FeedItem::new("Title", "https://example.com/item", "Description").unwrap(),
+            )
+            .unwrap();
+        (&db_conn).delete_feed(feed_uuid, Option::None).unwrap();
+        assert!((&db_conn).get_feed(feed_uuid).is_err());
+        assert_eq!(count(&db_conn, "feeds"), 0);
+        assert_eq!(count(&db_conn, "feed_items"), 0);
+    }
+
+    #[test]
+    fn feed_items_test() {
+        let db_conn: Connection = connect();
+        let feed: model::Feed = (&db_conn)
+            .create_feed(model::Feed::new("Title", "Description", "https://example.com").unwrap())
+            .unwrap();
+        let feed_uuid: Uuid = feed.get_uuid().unwrap();
+
+        let feed_item: model::FeedItem = (&db_conn)
+            .create_feed_item(
+                feed.clone(),
+                model::FeedItem::new("Title", "https://example.com/item", "Description").unwrap(),
+            )
+            .unwrap();
+        let item_uuid: Uuid = feed_item.get_uuid().unwrap();
+        let checksum: String = (&db_conn).get_feed_checksum(feed_uuid).unwrap();
+        assert_ne!(Option::Some(checksum.clone()), feed.get_checksum());
+
+        // The item is updated along with the checksum of its feed
+        let mut changed_feed_item: model::FeedItem = feed_item.clone();
+        changed_feed_item.title = Option::Some("Updated".to_string());
+        changed_feed_item.published_at = Option::None;
+        let updated_feed_item: model::FeedItem = (&db_conn)
+            .update_feed_item(
+                feed.clone(),
+                item_uuid,
+                changed_feed_item,
+                feed_item.get_checksum(),
+            )
+            .unwrap();
+        assert_eq!(updated_feed_item.title, Option::Some("Updated".to_string()));
+        // The publication time is kept when the update does not give one
+        assert_eq!(
+            updated_feed_item
+                .published_at
+                .map(|value| time::to_rfc3339(&value)),
+            feed_item.published_at.map(|value| time::to_rfc3339(&value))
+        );
+        assert_ne!((&db_conn).get_feed_checksum(feed_uuid).unwrap(), checksum);
+
+        let archived: model::FeedItem = (&db_conn)
+            .set_feed_item_state(feed.clone(), item_uuid, ItemState::Archived, Option::None)
+            .unwrap();
+        assert_eq!(archived.get_state(), ItemState::Archived);
+        let lookup: ItemsLookup = (&db_conn)
+            .get_feed_items(feed.clone(), Option::Some(vec![item_uuid]), false)
+            .unwrap();
+        assert_eq!(lookup.missing, vec![item_uuid]);
+
+        // Stale checksums do not delete anything
+        let result =
+            (&db_conn).delete_feed_item(feed.clone(), item_uuid, Option::Some("stale".to_string()));
+        assert_eq!(result.unwrap_err().code(), "checksum_mismatch");
+        assert_eq!(count(&db_conn, "feed_items"), 1);
+
+        (&db_conn)
+            .delete_feed_item(feed.clone(), item_uuid, Option::None)
+            .unwrap();
+        assert_eq!(count(&db_conn, "feed_items"), 0);
+        assert!((&db_conn).get_feed_item(feed, item_uuid).is_err());
+    }
+
+    #[test]
+    fn prune_feed_items_test() {
+        let db_conn: Connection = connect();
+        let feed: model::Feed = (&db_conn)
+            .create_feed(model::Feed::new("Title", "Description", "https://example.com").unwrap())
+            .unwrap();
+
+        let mut uuids: Vec<Uuid> = Vec::new();
+        for _ in 0..3 {
+            let feed_item: model::FeedItem = (&db_conn)
+                .create_feed_item(
+                    feed.clone(),
+                    model::FeedItem::new("Title", "https://example.com/item", "Description")
+                        .unwrap(),
+                )
+                .unwrap();
+            uuids.push(feed_item.get_uuid().unwrap());
+        }
+
+        let archived: Vec<Uuid> = (&db_conn)
+            .prune_feed_items(feed.clone(), vec![uuids[0]], RetentionAction::Archive)
+            .unwrap();
+        assert_eq!(archived, vec![uuids[0]]);
+
+        // Archived items are not published anymore, so they are not deleted
+        let deleted: Vec<Uuid> = (&db_conn)
+            .prune_feed_items(feed, uuids.clone(), RetentionAction::Delete)
+            .unwrap();
+        assert_eq!(deleted.len(), 2);
+        assert!(!deleted.contains(&uuids[0]));
+        assert_eq!(count(&db_conn, "feed_items"), 1);
+    }
+
+    #[test]
+    fn transaction_test() {
+        let db_conn: Connection = connect();
+        let error: Error = create_error!(SCOPE, FeedDbError::FailedToSaveFeed);
+
+        // Transactions that are not committed are rolled back, along with the nested ones
+        {
+            let _transaction: Transaction = begin(&db_conn, error.clone()).unwrap();
+            let nested: Transaction = begin(&db_conn, error.clone()).unwrap();
+            (&db_conn)
+                .create_feed(
+                    model::Feed::new("Title", "Description", "https://example.com").unwrap(),
+                )
+                .unwrap();
+            commit(nested, error.clone()).unwrap();
+        }
+        assert_eq!(count(&db_conn, "feeds"), 0);
+
+        let transaction: Transaction = begin(&db_conn, error.clone()).unwrap();
+        (&db_conn)
+            .create_feed(model::Feed::new("Title", "Description", "https://example.com").unwrap())
+            .unwrap();
+        commit(transaction, error).unwrap();
+        assert_eq!(count(&db_conn, "feeds"), 1);
+    }
+}
@@ -7,7 +7,6 @@ extern crate lazy_static;
 extern crate mongodb;
 #[macro_use]
 extern crate rocket;
-#[macro_use]
 extern crate rocket_contrib;
 #[macro_use]
 extern crate serde_derive;
@@ -20,6 +19,7 @@ mod common;
 #[macro_use]
 mod db;
 mod router;
+mod syndication;
 
 use std::env;
 
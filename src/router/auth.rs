@@ -0,0 +1,57 @@
+use super::AdminToken;
+
+use crate::common::errors::{AuthError, Error};
+
+use crypto::util::fixed_time_eq;
+use rocket::{
+    request::{self, FromRequest, Request},
+    Outcome, State,
+};
+
+/// The bearer token sent in the Authorization header of a request
+pub struct Authorization {
+    token: Option<String>,
+    admin_token: Option<String>,
+}
+
+impl Authorization {
+    /// Check that the request was sent by an administrator
+    /// Nobody is an administrator if no admin token is configured
+    pub fn require_admin(&self, scope: &str) -> Result<(), Error> {
+        let token: &String;
+        match &self.token {
+            Some(value) => token = value,
+            None => return Result::Err(create_error!(scope, AuthError::TokenRequired)),
+        }
+
+        match &self.admin_token {
+            Some(value) if fixed_time_eq(value.as_bytes(), token.as_bytes()) => Result::Ok(()),
+            _ => Result::Err(create_error!(scope, AuthError::InvalidToken)),
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Authorization {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let token: Option<String> = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| {
+                let mut parts = value.trim().splitn(2, ' ');
+                match (parts.next(), parts.next()) {
+                    (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+                        Option::Some(token.trim().to_string())
+                    }
+                    _ => Option::None,
+                }
+            });
+        let admin_token: Option<String> = match request.guard::<State<AdminToken>>() {
+            Outcome::Success(value) => value.0.clone(),
+            _ => Option::None,
+        };
+
+        Outcome::Success(Authorization { token, admin_token })
+    }
+}
@@ -0,0 +1,313 @@
+use super::RequireIfMatch;
+
+use crate::{
+    common::{
+        errors::{Error, PreconditionError},
+        time,
+    },
+    db::model::{Feed, FeedItem},
+};
+
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::{Header, Status},
+    request::{self, FromRequest, Request},
+    response::{self, Responder, Response},
+    Outcome, State,
+};
+
+/// The validators of a representation, sent in the ETag and Last-Modified headers
+#[derive(Clone, Debug, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validators {
+    /// Get the validators of a representation of a feed(or of its items)
+    pub fn of_feed(feed: &Feed, representation: Option<&str>) -> Self {
+        Validators {
+            etag: entity_tag(feed.get_checksum(), representation),
+            last_modified: feed.get_updated_at(),
+        }
+    }
+
+    /// Get the validators of a feed item, whose checksum its modifications are conditioned on
+    pub fn of_item(feed_item: &FeedItem) -> Self {
+        Validators {
+            etag: entity_tag(feed_item.get_checksum(), Option::None),
+            last_modified: feed_item.get_updated_at(),
+        }
+    }
+}
+
+/// The conditions of a conditional GET, sent in the If-None-Match and If-Modified-Since headers
+pub struct GetConditions {
+    etags: Vec<String>,
+    modified_since: Option<DateTime<Utc>>,
+}
+
+impl GetConditions {
+    /// Check if the client already has the representation with the given validators
+    /// Entity tags are compared weakly, and If-Modified-Since is only used when there is no
+    /// If-None-Match header, as required by RFC 7232
+    pub fn is_fresh(&self, validators: &Validators) -> bool {
+        if !self.etags.is_empty() {
+            return match &validators.etag {
+                Some(etag) => self.etags.iter().any(|tag| {
+                    tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/")
+                }),
+                None => false,
+            };
+        }
+
+        match (self.modified_since, validators.last_modified) {
+            // http dates have a precision of one second
+            (Some(since), Some(last_modified)) => last_modified.timestamp() <= since.timestamp(),
+            _ => false,
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for GetConditions {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let etags: Vec<String> = request
+            .headers()
+            .get("If-None-Match")
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        // Invalid dates are ignored
+        let modified_since: Option<DateTime<Utc>> = request
+            .headers()
+            .get_one("If-Modified-Since")
+            .and_then(time::from_http_date);
+
+        Outcome::Success(GetConditions {
+            etags,
+            modified_since,
+        })
+    }
+}
+
+/// The entity tags sent by a client in the If-Match header, along with whether modifications must
+/// be conditioned on a checksum
+pub struct IfMatch {
+    tags: Vec<String>,
+    required: bool,
+}
+
+impl IfMatch {
+    /// Get the checksum a modification is conditioned on, from the If-Match header or else from
+    /// the checksum of the received model
+    /// Only the first entity tag is used, "*" conditions the modification only on the existence of
+    /// the resource
+    /// Entity tags are compared strongly, as required by RFC 7232, so weak ones are rejected
+    pub fn checksum(
+        &self,
+        model_checksum: Option<String>,
+        scope: &str,
+    ) -> Result<Option<String>, Error> {
+        match self.tags.first() {
+            Some(tag) if tag == "*" => Result::Ok(Option::None),
+            Some(tag) => match checksum_of(tag) {
+                Some(value) => Result::Ok(Option::Some(value)),
+                None => Result::Err(create_error!(scope, PreconditionError::WeakEntityTag)),
+            },
+            None => match model_checksum {
+                Some(value) => Result::Ok(Option::Some(value)),
+                None if self.required => {
+                    Result::Err(create_error!(scope, PreconditionError::ChecksumRequired))
+                }
+                None => Result::Ok(Option::None),
+            },
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for IfMatch {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let tags: Vec<String> = request
+            .headers()
+            .get("If-Match")
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        let required: bool = match request.guard::<State<RequireIfMatch>>() {
+            Outcome::Success(value) => value.0,
+            _ => false,
+        };
+
+        Outcome::Success(IfMatch { tags, required })
+    }
+}
+
+/// Get the checksum a strong entity tag was created from
+/// Weak entity tags are not created from a checksum
+fn checksum_of(etag: &str) -> Option<String> {
+    if etag.starts_with("W/") {
+        return Option::None;
+    }
+
+    let opaque: &str = etag.trim_matches('"');
+    // Representations other than the default one are suffixed with their name
+    match opaque.find('-') {
+        Some(index) => Option::Some(opaque[..index].to_string()),
+        None => Option::Some(opaque.to_string()),
+    }
+}
+
+/// Create a strong entity tag out of a checksum
+/// Representations other than the default one(e.g. rss) are told apart by their name
+pub fn entity_tag(checksum: Option<String>, representation: Option<&str>) -> Option<String> {
+    let good_checksum: String = checksum?;
+    match representation {
+        Some(value) => Option::Some(format!("\"{}-{}\"", good_checksum, value)),
+        None => Option::Some(format!("\"{}\"", good_checksum)),
+    }
+}
+
+/// A response to a conditional request
+pub enum Conditional<R> {
+    /// The response, sent along with the validators of its representation
+    Modified(R, Validators),
+    /// The client already has the current representation
+    NotModified(Validators),
+}
+
+impl<R> Conditional<R> {
+    /// Answer with 304 Not Modified if the client already has the representation with the given
+    /// validators, otherwise produce the response
+    pub fn new<F>(conditions: &GetConditions, validators: Validators, response: F) -> Self
+    where
+        F: FnOnce() -> R,
+    {
+        if conditions.is_fresh(&validators) {
+            return Conditional::NotModified(validators);
+        }
+        Conditional::Modified(response(), validators)
+    }
+
+    /// A response without validators(e.g. an error)
+    pub fn untagged(response: R) -> Self {
+        Conditional::Modified(response, Validators::default())
+    }
+}
+
+/// Set the validator headers of a response
+fn set_validators(response: &mut Response, validators: Validators) {
+    if let Some(value) = validators.etag {
+        response.set_header(Header::new("ETag", value));
+    }
+    if let Some(value) = validators.last_modified {
+        response.set_header(Header::new("Last-Modified", time::to_http_date(&value)));
+    }
+}
+
+impl<'r, R: Responder<'r>> Responder<'r> for Conditional<R> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        match self {
+            Conditional::Modified(response, validators) => {
+                let mut built: Response<'r> = response.respond_to(request)?;
+                // Error responses are never tagged
+                if built.status().class().is_success() {
+                    set_validators(&mut built, validators);
+                }
+                Result::Ok(built)
+            }
+            Conditional::NotModified(validators) => {
+                let mut built: Response<'r> =
+                    Response::build().status(Status::NotModified).finalize();
+                set_validators(&mut built, validators);
+                Result::Ok(built)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checksum_of, entity_tag, GetConditions, IfMatch, Validators};
+
+    use chrono::{Duration, Utc};
+
+    /// Conditions with the given entity tags and no modification time
+    fn if_none_match(etags: &[&str]) -> GetConditions {
+        GetConditions {
+            etags: etags.iter().map(|tag| tag.to_string()).collect(),
+            modified_since: Option::None,
+        }
+    }
+
+    #[test]
+    fn if_none_match_test() {
+        let etag = entity_tag(Option::Some("abc".to_string()), Option::None).unwrap();
+        assert_eq!(etag, "\"abc\"");
+        let validators = Validators {
+            etag: Option::Some(etag),
+            last_modified: Option::None,
+        };
+
+        assert!(if_none_match(&["\"def\"", "W/\"abc\""]).is_fresh(&validators));
+        assert!(if_none_match(&["*"]).is_fresh(&validators));
+        assert!(!if_none_match(&["\"abc-rss\""]).is_fresh(&validators));
+        assert!(!if_none_match(&[]).is_fresh(&validators));
+    }
+
+    #[test]
+    fn if_modified_since_test() {
+        let now = Utc::now();
+        let validators = Validators {
+            etag: Option::Some("\"abc\"".to_string()),
+            last_modified: Option::Some(now),
+        };
+        let conditions = |since| GetConditions {
+            etags: Vec::new(),
+            modified_since: Option::Some(since),
+        };
+
+        assert!(conditions(now).is_fresh(&validators));
+        assert!(!conditions(now - Duration::seconds(2)).is_fresh(&validators));
+
+        // If-None-Match takes precedence
+        let mut precedence = conditions(now);
+        precedence.etags.push("\"def\"".to_string());
+        assert!(!precedence.is_fresh(&validators));
+    }
+
+    #[test]
+    fn if_match_test() {
+        let if_match = |tag: &str| IfMatch {
+            tags: vec![tag.to_string()],
+            required: true,
+        };
+
+        assert_eq!(
+            if_match("\"abc\"").checksum(Option::None, "test").unwrap(),
+            Option::Some("abc".to_string())
+        );
+        assert_eq!(
+            if_match("*").checksum(Option::None, "test").unwrap(),
+            Option::None
+        );
+
+        // Weak entity tags never match strongly
+        let e = if_match("W/\"abc\"")
+            .checksum(Option::None, "test")
+            .unwrap_err();
+        assert_eq!(e.code(), "etag_weak");
+    }
+
+    #[test]
+    fn checksum_of_test() {
+        assert_eq!(checksum_of("\"abc\""), Option::Some("abc".to_string()));
+        assert_eq!(checksum_of("\"abc-rss\""), Option::Some("abc".to_string()));
+        assert_eq!(checksum_of("W/\"abc\""), Option::None);
+    }
+}
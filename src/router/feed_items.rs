@@ -1,92 +1,221 @@
-use super::check_uuid;
+use super::{
+    auth::Authorization,
+    check_uuid,
+    conditional::{Conditional, GetConditions, IfMatch, Validators},
+    patch::{self, PatchDocument},
+};
 
 use crate::{
-    common::{report::Report, JsonResult},
+    common::{errors::Error, report::Report, JsonResult},
     db::{
-        model::{Feed, FeedItem},
-        DbConnection, FeedItemWrapper, FeedWrapper,
+        model::{aux::ItemState, Feed, FeedItem},
+        query::{ItemOrder, ItemPagination, ItemQuery, Page},
+        FeedItemWrapper, FeedWrapper, ItemsLookup, Store,
     },
     json_result,
 };
 
-use std::{option::Option, result::Result, vec::Vec};
+use std::{option::Option, result::Result, str::FromStr, vec::Vec};
 
+use json_patch::Patch;
 use log::*;
 use rocket_contrib::json::Json;
+use serde_json::Value;
 use uuid::Uuid;
 
 const SCOPE: &str = "router/feed_items";
 
-#[get("/feeds/<feed_uuid>/items")]
-pub fn get_all_feed_items(db_conn: DbConnection, feed_uuid: String) -> JsonResult<Vec<FeedItem>> {
-    // Check if the uuids are valid
-    let good_feed_uuid: Uuid;
-    match check_uuid(feed_uuid, SCOPE) {
-        Ok(value) => good_feed_uuid = value,
+/// Get the feed with the uuid received by a route
+fn find_feed(store: &Store, feed_uuid: String) -> Result<Feed, Error> {
+    let good_feed_uuid: Uuid = check_uuid(feed_uuid, SCOPE)?;
+    store.clone().get_feed(good_feed_uuid)
+}
+
+// The checksum and the update time of a feed cover all of its published items, so they are used
+// as the validators of the items read through the feed
+// Items that are not published are not covered by them, so listings that include such items are
+// not tagged
+// A single item read on its own is tagged with its own checksum instead, so that its entity tag
+// can be sent back in the If-Match header of its modifications
+
+/// Check that the scheduled items of a feed are only included for administrators
+fn check_include_scheduled(
+    authorization: &Authorization,
+    include_scheduled: Option<bool>,
+) -> Result<bool, Error> {
+    match include_scheduled {
+        Some(true) => {
+            authorization.require_admin(SCOPE)?;
+            Result::Ok(true)
+        }
+        _ => Result::Ok(false),
+    }
+}
+
+/// Check that only administrators list the items of a feed that are not published
+fn check_state(authorization: &Authorization, state: Option<String>) -> Result<ItemState, Error> {
+    match state {
+        Some(value) => {
+            let good_state: ItemState = ItemState::from_str(value.as_str())?;
+            if good_state != ItemState::Published {
+                authorization.require_admin(SCOPE)?;
+            }
+            Result::Ok(good_state)
+        }
+        None => Result::Ok(ItemState::Published),
+    }
+}
+
+#[get("/feeds/<feed_uuid>/items?<limit>&<cursor>&<order>&<state>&<include_scheduled>")]
+pub fn get_all_feed_items(
+    store: Store,
+    conditions: GetConditions,
+    authorization: Authorization,
+    feed_uuid: String,
+    limit: Option<u32>,
+    cursor: Option<String>,
+    order: Option<String>,
+    state: Option<String>,
+    include_scheduled: Option<bool>,
+) -> Conditional<JsonResult<Page<FeedItem>>> {
+    let good_include_scheduled: bool;
+    match check_include_scheduled(&authorization, include_scheduled) {
+        Ok(value) => good_include_scheduled = value,
         Err(e) => {
-            json_result!(Result::Err(e));
+            warn!("unauthorized request for scheduled items");
+            return Conditional::untagged(Result::Err(e));
+        }
+    }
+    let good_state: ItemState;
+    match check_state(&authorization, state) {
+        Ok(value) => good_state = value,
+        Err(e) => {
+            warn!("invalid state received");
+            return Conditional::untagged(Result::Err(e));
         }
     }
 
-    // Check if the feed exists and get its feed items uuids
-    let feed: Feed;
-    match (&*db_conn).clone().get_feed(good_feed_uuid) {
-        Ok(value) => feed = value,
+    let pagination: ItemPagination;
+    match ItemPagination::new(limit, cursor) {
+        Ok(value) => pagination = value,
         Err(e) => {
-            json_result!(Result::Err(e));
+            warn!("invalid pagination received");
+            return Conditional::untagged(Result::Err(e));
         }
     }
 
-    json_result!((&*db_conn).clone().get_feed_items(feed, Option::None))
+    let mut good_order: ItemOrder = ItemOrder::default();
+    if let Some(value) = order {
+        match ItemOrder::from_str(value.as_str()) {
+            Ok(_value) => good_order = _value,
+            Err(e) => {
+                warn!("invalid order received");
+                return Conditional::untagged(Result::Err(e));
+            }
+        }
+    }
+
+    // Check if the feed exists
+    let feed: Feed;
+    match find_feed(&store, feed_uuid) {
+        Ok(value) => feed = value,
+        Err(e) => return Conditional::untagged(Result::Err(e)),
+    }
+
+    let query: ItemQuery = ItemQuery {
+        pagination,
+        order: good_order,
+        state: good_state,
+        include_scheduled: good_include_scheduled,
+    };
+    if good_include_scheduled || good_state != ItemState::Published {
+        return Conditional::untagged(store.clone().list_feed_items(feed, query).map(Json));
+    }
+    let validators: Validators = Validators::of_feed(&feed, Option::None);
+    Conditional::new(&conditions, validators, || {
+        store.clone().list_feed_items(feed, query).map(Json)
+    })
 }
 
-#[get("/feeds/<feed_uuid>/items/<item_uuids>")]
+/// Get some items of a feed, or all of them if no uuids are given
+/// Only published items are returned, unless administrators include the scheduled ones, which
+/// also includes the drafts and the archived items
+#[get("/feeds/<feed_uuid>/items/<item_uuids>?<include_scheduled>")]
 pub fn get_specific_feed_items(
-    db_conn: DbConnection,
+    store: Store,
+    conditions: GetConditions,
+    authorization: Authorization,
     feed_uuid: String,
     item_uuids: Option<String>,
-) -> JsonResult<Vec<FeedItem>> {
-    // Check if the uuids are valid
-    let good_feed_uuid: Uuid;
-    match check_uuid(feed_uuid, SCOPE) {
-        Ok(value) => good_feed_uuid = value,
+    include_scheduled: Option<bool>,
+) -> Conditional<JsonResult<ItemsLookup>> {
+    let good_include_scheduled: bool;
+    match check_include_scheduled(&authorization, include_scheduled) {
+        Ok(value) => good_include_scheduled = value,
         Err(e) => {
-            json_result!(Result::Err(e));
+            warn!("unauthorized request for scheduled items");
+            return Conditional::untagged(Result::Err(e));
         }
     }
 
-    // Check if the feed exists and get its feed items uuids
-    let feed: Feed;
-    match (&*db_conn).clone().get_feed(good_feed_uuid) {
-        Ok(value) => feed = value,
-        Err(e) => {
-            json_result!(Result::Err(e));
+    // Check if the item uuids are valid
+    let mut good_item_uuids: Option<Vec<Uuid>> = Option::None;
+    if let Some(value) = item_uuids {
+        let mut uuids: Vec<Uuid> = Vec::new();
+        for item_uuid in value.split(',') {
+            match check_uuid(item_uuid.to_string(), SCOPE) {
+                Ok(_value) => uuids.push(_value),
+                Err(e) => return Conditional::untagged(Result::Err(e)),
+            }
         }
+        good_item_uuids = Option::Some(uuids);
+    } else {
+        info!("no item uuids found, fetching all items for this feed");
     }
 
-    if item_uuids.is_none() {
-        info!("no item uuids found, fetching all items for this feed");
-        json_result!((&*db_conn).clone().get_feed_items(feed, Option::None))
+    // Check if the feed exists
+    let feed: Feed;
+    match find_feed(&store, feed_uuid) {
+        Ok(value) => feed = value,
+        Err(e) => return Conditional::untagged(Result::Err(e)),
     }
 
-    let mut good_item_uuids: Vec<Uuid> = Vec::new();
-    for item_uuid in item_uuids.unwrap().split(',') {
-        match check_uuid(item_uuid.to_string(), SCOPE) {
-            Ok(value) => good_item_uuids.push(value),
-            Err(e) => {
-                json_result!(Result::Err(e));
-            }
+    if good_include_scheduled {
+        return Conditional::untagged(
+            store
+                .clone()
+                .get_feed_items(feed, good_item_uuids, true)
+                .map(Json),
+        );
+    }
+    if good_item_uuids
+        .as_ref()
+        .map_or(false, |uuids| uuids.len() == 1)
+    {
+        let lookup: ItemsLookup;
+        match store.clone().get_feed_items(feed, good_item_uuids, false) {
+            Ok(value) => lookup = value,
+            Err(e) => return Conditional::untagged(Result::Err(e)),
         }
+        let validators: Validators = match lookup.items.first() {
+            Some(feed_item) => Validators::of_item(feed_item),
+            None => return Conditional::untagged(Result::Ok(Json(lookup))),
+        };
+        return Conditional::new(&conditions, validators, || Result::Ok(Json(lookup)));
     }
 
-    json_result!((&*db_conn)
-        .clone()
-        .get_feed_items(feed, Option::Some(good_item_uuids)))
+    let validators: Validators = Validators::of_feed(&feed, Option::None);
+    Conditional::new(&conditions, validators, || {
+        store
+            .clone()
+            .get_feed_items(feed, good_item_uuids, false)
+            .map(Json)
+    })
 }
 
 #[get("/feeds/<feed_uuid>/items/<item_uuid>/checksum")]
 pub fn get_feed_item_checksum(
-    db_conn: DbConnection,
+    store: Store,
     feed_uuid: String,
     item_uuid: String,
 ) -> JsonResult<String> {
@@ -108,16 +237,14 @@ pub fn get_feed_item_checksum(
 
     // Check if the feed exists and get its feed items uuids
     let feed: Feed;
-    match (&*db_conn).clone().get_feed(good_feed_uuid) {
+    match store.clone().get_feed(good_feed_uuid) {
         Ok(value) => feed = value,
         Err(e) => {
             json_result!(Result::Err(e));
         }
     }
 
-    json_result!((&*db_conn)
-        .clone()
-        .get_feed_item_checksum(feed, good_item_uuid))
+    json_result!(store.clone().get_feed_item_checksum(feed, good_item_uuid))
 }
 
 #[post(
@@ -126,10 +253,13 @@ pub fn get_feed_item_checksum(
     data = "<model>"
 )]
 pub fn create_feed_item(
-    db_conn: DbConnection,
+    store: Store,
+    authorization: Authorization,
     feed_uuid: String,
     model: Json<FeedItem>,
 ) -> JsonResult<FeedItem> {
+    authorization.require_admin(SCOPE)?;
+
     // Check if the uuids are valid
     let good_feed_uuid: Uuid;
     match check_uuid(feed_uuid, SCOPE) {
@@ -141,14 +271,14 @@ pub fn create_feed_item(
 
     // Check if the feed exists and get its feed items uuids
     let feed: Feed;
-    match (&*db_conn).clone().get_feed(good_feed_uuid) {
+    match store.clone().get_feed(good_feed_uuid) {
         Ok(value) => feed = value,
         Err(e) => {
             json_result!(Result::Err(e));
         }
     }
 
-    json_result!((&*db_conn).clone().create_feed_item(feed, model.0))
+    json_result!(store.clone().create_feed_item(feed, model.0))
 }
 
 #[put(
@@ -157,11 +287,15 @@ pub fn create_feed_item(
     data = "<feed_item>"
 )]
 pub fn update_feed_item(
-    db_conn: DbConnection,
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
     feed_uuid: String,
     item_uuid: String,
     feed_item: Json<FeedItem>,
 ) -> JsonResult<FeedItem> {
+    authorization.require_admin(SCOPE)?;
+
     // Check if the uuids are valid
     let good_feed_uuid: Uuid;
     match check_uuid(feed_uuid, SCOPE) {
@@ -180,24 +314,163 @@ pub fn update_feed_item(
 
     // Check if the feed exists and get its feed items uuids
     let feed: Feed;
-    match (&*db_conn).clone().get_feed(good_feed_uuid) {
+    match store.clone().get_feed(good_feed_uuid) {
         Ok(value) => feed = value,
         Err(e) => {
             json_result!(Result::Err(e));
         }
     }
 
-    json_result!((&*db_conn)
+    let checksum: Option<String> = if_match.checksum(feed_item.0.get_checksum(), SCOPE)?;
+
+    json_result!(store
+        .clone()
+        .update_feed_item(feed, good_item_uuid, feed_item.0, checksum))
+}
+
+/// Apply a patch document on top of a stored feed item
+fn patch_feed_item(
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
+    feed_uuid: String,
+    item_uuid: String,
+    document: PatchDocument,
+) -> JsonResult<FeedItem> {
+    authorization.require_admin(SCOPE)?;
+    let checksum: Option<String> = if_match.checksum(Option::None, SCOPE)?;
+
+    let good_item_uuid: Uuid = check_uuid(item_uuid, SCOPE)?;
+    let feed: Feed = find_feed(&store, feed_uuid)?;
+    let feed_item: FeedItem = store.clone().get_feed_item(feed.clone(), good_item_uuid)?;
+
+    let patched: FeedItem = patch::apply(&feed_item, &document)?;
+    if let Some(e) = patched.validate() {
+        warn!("invalid patched model");
+        json_result!(Result::Err(e))
+    }
+
+    // The patch was applied on top of the stored item, so the update only happens if the item
+    // did not change in the meantime
+    json_result!(store.clone().update_feed_item(
+        feed,
+        good_item_uuid,
+        patched,
+        checksum.or_else(|| feed_item.get_checksum())
+    ))
+}
+
+#[patch(
+    "/feeds/<feed_uuid>/items/<item_uuid>",
+    format = "application/merge-patch+json",
+    data = "<document>"
+)]
+pub fn merge_patch_feed_item(
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
+    feed_uuid: String,
+    item_uuid: String,
+    document: Json<Value>,
+) -> JsonResult<FeedItem> {
+    patch_feed_item(
+        store,
+        authorization,
+        if_match,
+        feed_uuid,
+        item_uuid,
+        PatchDocument::Merge(document.0),
+    )
+}
+
+#[patch(
+    "/feeds/<feed_uuid>/items/<item_uuid>",
+    format = "application/json-patch+json",
+    data = "<document>"
+)]
+pub fn json_patch_feed_item(
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
+    feed_uuid: String,
+    item_uuid: String,
+    document: Json<Patch>,
+) -> JsonResult<FeedItem> {
+    patch_feed_item(
+        store,
+        authorization,
+        if_match,
+        feed_uuid,
+        item_uuid,
+        PatchDocument::Json(document.0),
+    )
+}
+
+/// Move a feed item to another stage of its lifecycle
+fn transition_feed_item(
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
+    feed_uuid: String,
+    item_uuid: String,
+    state: ItemState,
+) -> JsonResult<FeedItem> {
+    authorization.require_admin(SCOPE)?;
+    let feed: Feed = find_feed(&store, feed_uuid)?;
+    let good_item_uuid: Uuid = check_uuid(item_uuid, SCOPE)?;
+    let checksum: Option<String> = if_match.checksum(Option::None, SCOPE)?;
+
+    json_result!(store
         .clone()
-        .update_feed_item(feed, good_item_uuid, feed_item.0))
+        .set_feed_item_state(feed, good_item_uuid, state, checksum))
+}
+
+#[post("/feeds/<feed_uuid>/items/<item_uuid>/publish")]
+pub fn publish_feed_item(
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
+    feed_uuid: String,
+    item_uuid: String,
+) -> JsonResult<FeedItem> {
+    transition_feed_item(
+        store,
+        authorization,
+        if_match,
+        feed_uuid,
+        item_uuid,
+        ItemState::Published,
+    )
+}
+
+#[post("/feeds/<feed_uuid>/items/<item_uuid>/archive")]
+pub fn archive_feed_item(
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
+    feed_uuid: String,
+    item_uuid: String,
+) -> JsonResult<FeedItem> {
+    transition_feed_item(
+        store,
+        authorization,
+        if_match,
+        feed_uuid,
+        item_uuid,
+        ItemState::Archived,
+    )
 }
 
 #[delete("/feeds/<feed_uuid>/items/<item_uuid>")]
 pub fn delete_feed_item(
-    db_conn: DbConnection,
+    store: Store,
+    authorization: Authorization,
+    if_match: IfMatch,
     feed_uuid: String,
     item_uuid: String,
 ) -> JsonResult<Report<String>> {
+    authorization.require_admin(SCOPE)?;
+
     // Check if the uuids are valid
     let good_feed_uuid: Uuid;
     match check_uuid(feed_uuid, SCOPE) {
@@ -216,12 +489,370 @@ pub fn delete_feed_item(
 
     // Check if the feed exists and get its feed items uuids
     let feed: Feed;
-    match (&*db_conn).clone().get_feed(good_feed_uuid) {
+    match store.clone().get_feed(good_feed_uuid) {
         Ok(value) => feed = value,
         Err(e) => {
             json_result!(Result::Err(e));
         }
     }
 
-    json_result!((&*db_conn).clone().delete_feed_item(feed, good_item_uuid))
+    let checksum: Option<String> = if_match.checksum(Option::None, SCOPE)?;
+
+    json_result!(store
+        .clone()
+        .delete_feed_item(feed, good_item_uuid, checksum))
+}
+
+#[cfg(all(test, feature = "inmemory"))]
+mod test {
+    use super::super::{test_rocket, TEST_ADMIN_TOKEN};
+
+    use crate::db::{
+        model::{aux::ItemState, Feed, FeedItem},
+        query::Page,
+    };
+
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::Client,
+    };
+
+    /// The header that authorizes a request as sent by an administrator
+    fn admin() -> Header<'static> {
+        Header::new("Authorization", format!("Bearer {}", TEST_ADMIN_TOKEN))
+    }
+
+    /// Send a request with a json body as an administrator and read the json it answers with
+    fn post_json<T>(client: &Client, uri: String, body: serde_json::Value) -> T
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut response = client
+            .post(uri)
+            .header(ContentType::JSON)
+            .header(admin())
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&response.body_string().unwrap()).unwrap()
+    }
+
+    /// List the titles of the published items of a feed
+    fn published_titles(client: &Client, feed: &Feed) -> Vec<String> {
+        let mut response = client
+            .get(format!(
+                "/feeds/{}/items?order=oldest",
+                feed.get_uuid().unwrap()
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let page: Page<FeedItem> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        page.items
+            .into_iter()
+            .filter_map(|feed_item| feed_item.title)
+            .collect()
+    }
+
+    #[test]
+    fn feed_item_lifecycle_test() {
+        let client: Client = Client::new(test_rocket()).unwrap();
+        let feed: Feed = post_json(
+            &client,
+            "/feeds".to_string(),
+            serde_json::json!({
+                "title": "Title",
+                "description": "Description",
+                "link": "https://example.com",
+            }),
+        );
+        let items_uri: String = format!("/feeds/{}/items", feed.get_uuid().unwrap());
+        let body: String = serde_json::json!({
+            "title": "Item",
+            "description": "Description",
+            "link": "https://example.com/item",
+        })
+        .to_string();
+
+        // Only administrators write the items
+        let response = client
+            .post(items_uri.clone())
+            .header(ContentType::JSON)
+            .body(body.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .post(items_uri.clone())
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        post_json::<FeedItem>(
+            &client,
+            items_uri.clone(),
+            serde_json::json!({
+                "title": "Published",
+                "description": "Description",
+                "link": "https://example.com/published",
+            }),
+        );
+        let draft: FeedItem = post_json(
+            &client,
+            items_uri.clone(),
+            serde_json::json!({
+                "title": "Draft",
+                "description": "Description",
+                "link": "https://example.com/draft",
+                "state": "draft",
+            }),
+        );
+        assert_eq!(draft.get_state(), ItemState::Draft);
+        assert_eq!(published_titles(&client, &feed), vec!["Published"]);
+
+        // Only administrators list the drafts
+        let response = client.get(format!("{}?state=draft", items_uri)).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(
+            response.headers().get_one("WWW-Authenticate"),
+            Option::Some("Bearer")
+        );
+
+        let draft_uri: String = format!("{}/{}", items_uri, draft.get_uuid().unwrap());
+        let published: FeedItem = post_json(
+            &client,
+            format!("{}/publish", draft_uri),
+            serde_json::Value::Null,
+        );
+        assert_eq!(published.get_state(), ItemState::Published);
+        assert_eq!(published_titles(&client, &feed), vec!["Published", "Draft"]);
+
+        // Published items can not be published again, but they can be archived
+        let response = client
+            .post(format!("{}/publish", draft_uri))
+            .header(admin())
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        post_json::<FeedItem>(
+            &client,
+            format!("{}/archive", draft_uri),
+            serde_json::Value::Null,
+        );
+        assert_eq!(published_titles(&client, &feed), vec!["Published"]);
+
+        let response = client.delete(draft_uri.clone()).header(admin()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get(format!("{}/checksum", draft_uri)).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn update_with_read_etag_test() {
+        let client: Client = Client::new(test_rocket()).unwrap();
+        let feed: Feed = post_json(
+            &client,
+            "/feeds".to_string(),
+            serde_json::json!({
+                "title": "Title",
+                "description": "Description",
+                "link": "https://example.com",
+            }),
+        );
+        let items_uri: String = format!("/feeds/{}/items", feed.get_uuid().unwrap());
+        let feed_item: FeedItem = post_json(
+            &client,
+            items_uri.clone(),
+            serde_json::json!({
+                "title": "Item",
+                "description": "Description",
+                "link": "https://example.com/item",
+            }),
+        );
+        let item_uri: String = format!("{}/{}", items_uri, feed_item.get_uuid().unwrap());
+
+        let response = client.get(item_uri.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let etag: String = response.headers().get_one("ETag").unwrap().to_string();
+        assert_eq!(etag, format!("\"{}\"", feed_item.get_checksum().unwrap()));
+
+        let response = client
+            .get(item_uri.clone())
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+
+        let body: String = serde_json::json!({
+            "title": "Updated",
+            "description": "Description",
+            "link": "https://example.com/item",
+        })
+        .to_string();
+        let response = client
+            .put(item_uri.clone())
+            .header(ContentType::JSON)
+            .header(admin())
+            .header(Header::new("If-Match", etag.clone()))
+            .body(body.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        // The entity tag changed along with the item
+        let response = client
+            .put(item_uri)
+            .header(ContentType::JSON)
+            .header(admin())
+            .header(Header::new("If-Match", etag))
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::PreconditionFailed);
+    }
+
+    #[test]
+    fn transition_with_read_etag_test() {
+        let client: Client = Client::new(test_rocket()).unwrap();
+        let feed: Feed = post_json(
+            &client,
+            "/feeds".to_string(),
+            serde_json::json!({
+                "title": "Title",
+                "description": "Description",
+                "link": "https://example.com",
+            }),
+        );
+        let items_uri: String = format!("/feeds/{}/items", feed.get_uuid().unwrap());
+        let feed_item: FeedItem = post_json(
+            &client,
+            items_uri.clone(),
+            serde_json::json!({
+                "title": "Item",
+                "description": "Description",
+                "link": "https://example.com/item",
+            }),
+        );
+        let item_uri: String = format!("{}/{}", items_uri, feed_item.get_uuid().unwrap());
+
+        let response = client.get(item_uri.clone()).dispatch();
+        let etag: String = response.headers().get_one("ETag").unwrap().to_string();
+        let mut response = client
+            .post(format!("{}/archive", item_uri))
+            .header(admin())
+            .header(Header::new("If-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let archived: FeedItem = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(archived.get_state(), ItemState::Archived);
+
+        // The entity tag changed along with the state of the item
+        assert_ne!(archived.get_checksum(), feed_item.get_checksum());
+        let response = client
+            .post(format!("{}/publish", item_uri))
+            .header(admin())
+            .header(Header::new("If-Match", etag))
+            .dispatch();
+        assert_eq!(response.status(), Status::PreconditionFailed);
+
+        let response = client
+            .post(format!("{}/publish", item_uri))
+            .header(admin())
+            .header(Header::new(
+                "If-Match",
+                format!("\"{}\"", archived.get_checksum().unwrap()),
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn create_archived_feed_item_test() {
+        let client: Client = Client::new(test_rocket()).unwrap();
+        let feed: Feed = post_json(
+            &client,
+            "/feeds".to_string(),
+            serde_json::json!({
+                "title": "Title",
+                "description": "Description",
+                "link": "https://example.com",
+            }),
+        );
+
+        // Items are only archived through their transitions
+        let mut response = client
+            .post(format!("/feeds/{}/items", feed.get_uuid().unwrap()))
+            .header(ContentType::JSON)
+            .header(admin())
+            .body(
+                serde_json::json!({
+                    "title": "Item",
+                    "description": "Description",
+                    "link": "https://example.com/item",
+                    "state": "archived",
+                })
+                .to_string(),
+            )
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(body["code"], "state_invalid");
+    }
+
+    #[test]
+    fn paginate_feed_items_test() {
+        let client: Client = Client::new(test_rocket()).unwrap();
+        let feed: Feed = post_json(
+            &client,
+            "/feeds".to_string(),
+            serde_json::json!({
+                "title": "Title",
+                "description": "Description",
+                "link": "https://example.com",
+            }),
+        );
+        let items_uri: String = format!("/feeds/{}/items", feed.get_uuid().unwrap());
+        let create_item = |title: &str, published_at: &str| {
+            post_json::<FeedItem>(
+                &client,
+                items_uri.clone(),
+                serde_json::json!({
+                    "title": title,
+                    "description": "Description",
+                    "link": "https://example.com/item",
+                    "published_at": published_at,
+                }),
+            )
+        };
+        let list_items = |uri: String| {
+            let mut response = client.get(uri).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let page: Page<FeedItem> =
+                serde_json::from_str(&response.body_string().unwrap()).unwrap();
+            page
+        };
+        create_item("First", "2020-01-01T00:00:00.000Z");
+        create_item("Second", "2020-01-02T00:00:00.000Z");
+        create_item("Third", "2020-01-03T00:00:00.000Z");
+
+        let page: Page<FeedItem> = list_items(format!("{}?limit=2", items_uri));
+        let titles: Vec<String> = page
+            .items
+            .into_iter()
+            .filter_map(|item| item.title)
+            .collect();
+        assert_eq!(titles, vec!["Third".to_string(), "Second".to_string()]);
+
+        // Items created in the meantime do not shift the next page
+        create_item("Fourth", "2020-01-04T00:00:00.000Z");
+        let page: Page<FeedItem> = list_items(format!(
+            "{}?limit=2&cursor={}",
+            items_uri,
+            page.next.unwrap()
+        ));
+        let titles: Vec<String> = page
+            .items
+            .into_iter()
+            .filter_map(|item| item.title)
+            .collect();
+        assert_eq!(titles, vec!["First".to_string()]);
+        assert_eq!(page.next, Option::None);
+    }
 }
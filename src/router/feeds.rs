@@ -1,67 +1,73 @@
-use super::check_uuid;
+use super::{
+    auth::Authorization,
+    check_uuid,
+    conditional::{Conditional, GetConditions, IfMatch, Validators},
+    patch::{self, PatchDocument},
+    read_document,
+};
 
 use crate::{
     common::{
-        errors::{Error, FeedRouterError, ModelError},
+        errors::{Error, FeedRouterError},
         report::Report,
-        JsonResult,
+        DocumentResult, JsonResult,
+    },
+    db::{
+        model::Feed,
+        query::{FeedQuery, FeedSort, Page, Pagination},
+        retention::{self, PruneSummary},
+        FeedWrapper, Store,
     },
-    db::{model::Feed, DbConnection, FeedWrapper},
     json_result,
+    syndication::{
+        import::{self, ImportSummary, ImportedFeed},
+        Format,
+    },
 };
 
+use std::{cmp::Ordering, str::FromStr};
+
+use json_patch::Patch;
 use log::*;
+use rocket::{
+    http::{Accept, MediaType, QMediaType, Status},
+    request::Request,
+    response::{self, Content, Responder, Response},
+    Data,
+};
 use rocket_contrib::json::Json;
+use serde_json::Value;
 use uuid::Uuid;
 
 const SCOPE: &str = "router/feeds";
 
 /// Check a feed model used by create and update operations
-fn check_feed_model(model: &Feed) -> Result<Json<()>, Json<Error>> {
-    if model.title.is_none() {
-        warn!("invalid model: no title");
-        json_result!(Result::Err(create_error!(
-            SCOPE,
-            ModelError::ModelHasNoTitle
-        )))
-    }
-    if model.description.is_none() {
-        warn!("invalid model: no description");
-        json_result!(Result::Err(create_error!(
-            SCOPE,
-            ModelError::ModelHasNoDescription
-        )))
-    }
-    if model.link.is_none() {
-        warn!("invalid model: no link");
-        json_result!(Result::Err(create_error!(
-            SCOPE,
-            ModelError::ModelHasNoLink
-        )))
+fn check_feed_model(model: &Feed) -> JsonResult<()> {
+    if let Some(e) = model.validate() {
+        warn!("invalid model");
+        json_result!(Result::Err(e))
     }
     info!("valid model");
     Result::Ok(Json(()))
 }
 
-#[get("/feeds/<uuid>?<with_items>")]
-pub fn get_feed(db_conn: DbConnection, uuid: String, with_items: Option<bool>) -> JsonResult<Feed> {
+/// Get the feed with the uuid received by a route
+fn find_feed(store: &Store, uuid: String) -> Result<Feed, Error> {
     // Check if the uuid is valid and return if it's not
     let good_uuid: Uuid;
     match check_uuid(uuid, SCOPE) {
         Ok(value) => good_uuid = value,
         Err(e) => {
             warn!("invalid uuid received");
-            json_result!(Result::Err(e));
+            return Result::Err(e);
         }
     }
 
-    let mut feed: Feed;
-
-    match (&*db_conn).clone().get_feed(good_uuid) {
-        Ok(value) => feed = value,
-        Err(e) => json_result!(Result::Err(e)),
-    }
+    store.clone().get_feed(good_uuid)
+}
 
+/// Get a feed in feeder's own json representation
+fn get_feed_json(store: Store, mut feed: Feed, with_items: Option<bool>) -> JsonResult<Feed> {
     if with_items.is_none() {
         json_result!(Result::Ok(feed))
     }
@@ -69,16 +75,156 @@ pub fn get_feed(db_conn: DbConnection, uuid: String, with_items: Option<bool>) -
         json_result!(Result::Ok(feed))
     }
 
-    if let Some(e) = feed.with_items((&*db_conn).clone()) {
+    if let Some(e) = feed.with_items(store.clone()) {
         json_result!(Result::Err(e))
     }
     json_result!(Result::Ok(feed))
 }
 
+/// Response of the get feed route, depending on the negotiated media type
+#[derive(Responder)]
+pub enum FeedResponse {
+    Json(Json<Feed>),
+    Document(Content<String>),
+    Error(Error),
+    NotAcceptable(Status),
+}
+
+/// Response whose representation depends on the accept header of the request
+pub struct Negotiated<R>(pub R);
+
+impl<'r, R: Responder<'r>> Responder<'r> for Negotiated<R> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let mut response: Response<'r> = self.0.respond_to(request)?;
+        // Caches must not serve a representation to a client that asked for another one
+        response.set_raw_header("Vary", "Accept");
+        Result::Ok(response)
+    }
+}
+
+/// Choose the representation of a feed from the media types accepted by the client
+/// The media types are tried by decreasing quality, the json representation being the
+/// default one. None means that no accepted media type can be produced
+fn negotiate_format(accept: Option<&Accept>) -> Option<Option<Format>> {
+    // No accept header means that anything is accepted
+    let accept: &Accept = match accept {
+        Some(value) => value,
+        None => return Option::Some(Option::None),
+    };
+
+    // The sort is stable, so media types with the same quality keep the client's order
+    let mut media_types: Vec<&QMediaType> = accept
+        .iter()
+        .filter(|media_type| media_type.weight_or(1.0) > 0.0)
+        .collect();
+    media_types.sort_by(|a, b| {
+        b.weight_or(1.0)
+            .partial_cmp(&a.weight_or(1.0))
+            .unwrap_or(Ordering::Equal)
+    });
+
+    // Json is the default representation, so it is the one accepted by the wildcards
+    let any_application: MediaType = MediaType::new("application", "*");
+    for media_type in media_types {
+        let media_type: &MediaType = media_type.media_type();
+        if media_type == &MediaType::Any
+            || media_type == &any_application
+            || media_type == &MediaType::JSON
+        {
+            return Option::Some(Option::None);
+        }
+        if let Some(format) = Format::from_media_type(media_type) {
+            return Option::Some(Option::Some(format));
+        }
+    }
+    Option::None
+}
+
+#[get("/feeds/<uuid>?<with_items>")]
+pub fn get_feed(
+    store: Store,
+    accept: Option<&Accept>,
+    conditions: GetConditions,
+    uuid: String,
+    with_items: Option<bool>,
+) -> Negotiated<Conditional<FeedResponse>> {
+    let format: Option<Format>;
+    match negotiate_format(accept) {
+        Some(value) => format = value,
+        None => {
+            warn!("cannot produce a feed with the accepted media types");
+            return Negotiated(Conditional::untagged(FeedResponse::NotAcceptable(
+                Status::NotAcceptable,
+            )));
+        }
+    }
+
+    let feed: Feed;
+    match find_feed(&store, uuid) {
+        Ok(value) => feed = value,
+        Err(e) => return Negotiated(Conditional::untagged(FeedResponse::Error(e))),
+    }
+
+    let validators: Validators = match format {
+        Some(value) => Validators::of_feed(&feed, Option::Some(value.name())),
+        None if with_items.unwrap_or(false) => Validators::of_feed(&feed, Option::Some("items")),
+        None => Validators::of_feed(&feed, Option::None),
+    };
+
+    Negotiated(Conditional::new(&conditions, validators, || match format {
+        Some(value) => match render_feed(store, feed, value) {
+            Ok(document) => FeedResponse::Document(document),
+            Err(e) => FeedResponse::Error(e),
+        },
+        None => match get_feed_json(store, feed, with_items) {
+            Ok(json) => FeedResponse::Json(json),
+            Err(e) => FeedResponse::Error(e),
+        },
+    }))
+}
+
+#[get("/feeds?<limit>&<cursor>&<sort>&<category>&<language>")]
+pub fn list_feeds(
+    store: Store,
+    limit: Option<u32>,
+    cursor: Option<String>,
+    sort: Option<String>,
+    category: Option<String>,
+    language: Option<String>,
+) -> JsonResult<Page<Feed>> {
+    let pagination: Pagination;
+    match Pagination::new(limit, cursor) {
+        Ok(value) => pagination = value,
+        Err(e) => {
+            warn!("invalid pagination received");
+            json_result!(Result::Err(e))
+        }
+    }
+
+    let mut good_sort: FeedSort = FeedSort::default();
+    if let Some(value) = sort {
+        match FeedSort::from_str(value.as_str()) {
+            Ok(_value) => good_sort = _value,
+            Err(e) => {
+                warn!("invalid sort received");
+                json_result!(Result::Err(e))
+            }
+        }
+    }
+
+    let query: FeedQuery = FeedQuery {
+        pagination,
+        sort: good_sort,
+        category,
+        language,
+    };
+    json_result!(store.clone().list_feeds(query))
+}
+
 #[get("/feeds/<uuid>/checksum")]
-pub fn get_feed_checksum(db_conn: DbConnection, uuid: String) -> JsonResult<String> {
+pub fn get_feed_checksum(store: Store, uuid: String) -> JsonResult<String> {
     match check_uuid(uuid, SCOPE) {
-        Ok(value) => json_result!((&*db_conn).clone().get_feed_checksum(value)),
+        Ok(value) => json_result!(store.clone().get_feed_checksum(value)),
         Err(e) => {
             warn!("invalid uuid received");
             json_result!(Result::Err(e))
@@ -86,14 +232,141 @@ pub fn get_feed_checksum(db_conn: DbConnection, uuid: String) -> JsonResult<Stri
     }
 }
 
+/// Render a feed along with its items in a syndication format
+fn render_feed(store: Store, mut feed: Feed, format: Format) -> DocumentResult {
+    match feed.generate(store.clone(), format) {
+        Ok(value) => Result::Ok(Content(format.content_type(), value)),
+        Err(e) => Result::Err(e),
+    }
+}
+
+/// Render a feed in a syndication format, unless the client already has the rendered document
+fn render_feed_conditionally(
+    store: Store,
+    conditions: GetConditions,
+    uuid: String,
+    format: Format,
+) -> Conditional<DocumentResult> {
+    let feed: Feed;
+    match find_feed(&store, uuid) {
+        Ok(value) => feed = value,
+        Err(e) => return Conditional::untagged(Result::Err(e)),
+    }
+
+    let validators: Validators = Validators::of_feed(&feed, Option::Some(format.name()));
+    Conditional::new(&conditions, validators, || render_feed(store, feed, format))
+}
+
+#[get("/feeds/<uuid>/rss")]
+pub fn get_feed_rss(
+    store: Store,
+    conditions: GetConditions,
+    uuid: String,
+) -> Conditional<DocumentResult> {
+    render_feed_conditionally(store, conditions, uuid, Format::Rss)
+}
+
+#[get("/feeds/<uuid>/atom")]
+pub fn get_feed_atom(
+    store: Store,
+    conditions: GetConditions,
+    uuid: String,
+) -> Conditional<DocumentResult> {
+    render_feed_conditionally(store, conditions, uuid, Format::Atom)
+}
+
+#[get("/feeds/<uuid>/feed.json")]
+pub fn get_feed_json_feed(
+    store: Store,
+    conditions: GetConditions,
+    uuid: String,
+) -> Conditional<DocumentResult> {
+    render_feed_conditionally(store, conditions, uuid, Format::JsonFeed)
+}
+
 #[post("/feeds", format = "application/json", data = "<model>")]
-pub fn create_feed(db_conn: DbConnection, model: Json<Feed>) -> JsonResult<Feed> {
-    json_result!((&*db_conn).clone().create_feed(model.0))
+pub fn create_feed(store: Store, model: Json<Feed>) -> JsonResult<Feed> {
+    json_result!(store.clone().create_feed(model.0))
+}
+
+#[post("/feeds/import", data = "<data>")]
+pub fn import_feed(store: Store, data: Data) -> JsonResult<Report<ImportSummary>> {
+    let document: String;
+    match read_document(data, SCOPE) {
+        Ok(value) => document = value,
+        Err(e) => {
+            warn!("failed to receive the imported document");
+            json_result!(Result::Err(e))
+        }
+    }
+
+    let imported: ImportedFeed;
+    match import::parse(&document) {
+        Ok(value) => imported = value,
+        Err(e) => {
+            warn!("failed to read the imported document");
+            json_result!(Result::Err(e))
+        }
+    }
+
+    let created_feed: Feed;
+    match store.clone().create_feed(imported.feed) {
+        Ok(value) => created_feed = value,
+        Err(e) => json_result!(Result::Err(e)),
+    }
+    let feed_uuid: Uuid = created_feed.get_uuid().unwrap();
+
+    let mut imported_count: usize = 0;
+    let mut errors: Vec<String> = Vec::new();
+
+    // Documents list the newest items first, so the items are created in reverse order
+    // in order to end up with the same order as if they were created one by one
+    for item in imported.items.into_iter().rev() {
+        let model = match item {
+            Ok(value) => value,
+            Err(e) => {
+                info!("skipping imported item: {}", e);
+                errors.push(e);
+                continue;
+            }
+        };
+
+        match store.clone().create_feed_item(created_feed.clone(), model) {
+            Ok(_) => imported_count += 1,
+            Err(e) => {
+                warn!("failed to create imported item: {}", e);
+                errors.push(format!("{}", e));
+            }
+        }
+    }
+
+    // Get the feed again for its updated checksum
+    let feed: Feed;
+    match store.clone().get_feed(feed_uuid) {
+        Ok(value) => feed = value,
+        Err(e) => json_result!(Result::Err(e)),
+    }
+
+    let summary: ImportSummary = ImportSummary {
+        feed,
+        imported: imported_count,
+        skipped: errors.len(),
+        errors,
+    };
+    json_result!(Result::Ok(Report::new_with_data(
+        SCOPE.to_string(),
+        format!(
+            "imported {} items, skipped {} items",
+            summary.imported, summary.skipped
+        ),
+        summary
+    )))
 }
 
 #[put("/feeds?<uuid>", format = "application/json", data = "<model>")]
 pub fn update_feed(
-    db_conn: DbConnection,
+    store: Store,
+    if_match: IfMatch,
     uuid: Option<String>,
     model: Json<Feed>,
 ) -> JsonResult<Feed> {
@@ -137,16 +410,283 @@ pub fn update_feed(
 
     check_feed_model(&model.0)?;
 
-    json_result!((&*db_conn).clone().update_feed(good_uuid.unwrap(), model.0))
+    let checksum: Option<String> = if_match.checksum(model.0.get_checksum(), SCOPE)?;
+
+    json_result!(store
+        .clone()
+        .update_feed(good_uuid.unwrap(), model.0, checksum))
+}
+
+/// Apply a patch document on top of a stored feed
+fn patch_feed(
+    store: Store,
+    if_match: IfMatch,
+    uuid: String,
+    document: PatchDocument,
+) -> JsonResult<Feed> {
+    let checksum: Option<String> = if_match.checksum(Option::None, SCOPE)?;
+
+    let feed: Feed = find_feed(&store, uuid)?;
+    let patched: Feed = patch::apply(&feed, &document)?;
+    check_feed_model(&patched)?;
+
+    // The patch was applied on top of the stored feed, so the update only happens if the feed
+    // did not change in the meantime
+    json_result!(store.clone().update_feed(
+        feed.get_uuid().unwrap(),
+        patched,
+        checksum.or_else(|| feed.get_checksum())
+    ))
+}
+
+#[patch(
+    "/feeds/<uuid>",
+    format = "application/merge-patch+json",
+    data = "<document>"
+)]
+pub fn merge_patch_feed(
+    store: Store,
+    if_match: IfMatch,
+    uuid: String,
+    document: Json<Value>,
+) -> JsonResult<Feed> {
+    patch_feed(store, if_match, uuid, PatchDocument::Merge(document.0))
+}
+
+#[patch(
+    "/feeds/<uuid>",
+    format = "application/json-patch+json",
+    data = "<document>"
+)]
+pub fn json_patch_feed(
+    store: Store,
+    if_match: IfMatch,
+    uuid: String,
+    document: Json<Patch>,
+) -> JsonResult<Feed> {
+    patch_feed(store, if_match, uuid, PatchDocument::Json(document.0))
+}
+
+#[post("/feeds/<uuid>/prune")]
+pub fn prune_feed(
+    store: Store,
+    authorization: Authorization,
+    uuid: String,
+) -> JsonResult<Report<PruneSummary>> {
+    authorization.require_admin(SCOPE)?;
+    let good_uuid: Uuid = check_uuid(uuid, SCOPE)?;
+    let feed: Feed = store.clone().get_feed(good_uuid)?;
+
+    let summary: PruneSummary = retention::prune(store.clone(), feed)?;
+    json_result!(Result::Ok(Report::new_with_data(
+        SCOPE.to_string(),
+        format!("pruned {} items", summary.pruned.len()),
+        summary
+    )))
 }
 
 #[delete("/feeds/<uuid>")]
-pub fn delete_feed(db_conn: DbConnection, uuid: String) -> JsonResult<Report<String>> {
+pub fn delete_feed(store: Store, if_match: IfMatch, uuid: String) -> JsonResult<Report<String>> {
+    let checksum: Option<String> = if_match.checksum(Option::None, SCOPE)?;
+
     match check_uuid(uuid, SCOPE) {
-        Ok(value) => json_result!((&*db_conn).clone().delete_feed(value)),
+        Ok(value) => json_result!(store.clone().delete_feed(value, checksum)),
         Err(e) => {
             warn!("invalid uuid received");
             json_result!(Result::Err(e))
         }
     }
 }
+
+#[cfg(all(test, feature = "inmemory"))]
+mod test {
+    use crate::db::{model::Feed, query::Page};
+
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::Client,
+    };
+
+    /// Create a feed with the given title and category through the router
+    fn create_feed(client: &Client, title: &str, category: &str) -> Feed {
+        let mut response = client
+            .post("/feeds")
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!({
+                    "title": title,
+                    "description": "Description",
+                    "link": "https://example.com",
+                    "category": category,
+                })
+                .to_string(),
+            )
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&response.body_string().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn feed_lifecycle_test() {
+        let client: Client = Client::new(super::super::rocket()).unwrap();
+        let feed: Feed = create_feed(&client, "Title", "news");
+        let uri: String = format!("/feeds/{}", feed.get_uuid().unwrap());
+        let etag: String = format!("\"{}\"", feed.get_checksum().unwrap());
+
+        let response = client.get(uri.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.headers().get_one("ETag"),
+            Option::Some(etag.as_str())
+        );
+
+        let response = client
+            .get(uri.clone())
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+
+        // Stale checksums do not delete anything
+        let response = client
+            .delete(uri.clone())
+            .header(Header::new("If-Match", "\"stale\""))
+            .dispatch();
+        assert_eq!(response.status(), Status::PreconditionFailed);
+
+        let response = client
+            .delete(uri.clone())
+            .header(Header::new("If-Match", etag))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get(uri).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn update_feed_with_url_uuid_test() {
+        let client: Client = Client::new(super::super::rocket()).unwrap();
+        let feed: Feed = create_feed(&client, "Title", "news");
+
+        // The body does not hold the uuid of the feed
+        let mut response = client
+            .put(format!("/feeds?uuid={}", feed.get_uuid().unwrap()))
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!({
+                    "title": "Updated",
+                    "description": "Description",
+                    "link": "https://example.com",
+                })
+                .to_string(),
+            )
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let updated_feed: Feed = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(updated_feed.get_uuid(), feed.get_uuid());
+        assert_eq!(updated_feed.title, Option::Some("Updated".to_string()));
+    }
+
+    #[test]
+    fn negotiate_feed_test() {
+        let client: Client = Client::new(super::super::rocket()).unwrap();
+        let feed: Feed = create_feed(&client, "Title", "news");
+        let uri: String = format!("/feeds/{}", feed.get_uuid().unwrap());
+
+        // Browsers accept anything with a lower quality, which means the json representation
+        let response = client
+            .get(uri.clone())
+            .header(Header::new(
+                "Accept",
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Option::Some(ContentType::JSON));
+        assert_eq!(response.headers().get_one("Vary"), Option::Some("Accept"));
+
+        let response = client
+            .get(uri.clone())
+            .header(Header::new(
+                "Accept",
+                "application/rss+xml;q=0.5, application/atom+xml",
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.content_type(),
+            Option::Some(ContentType::new("application", "atom+xml"))
+        );
+        assert_eq!(response.headers().get_one("Vary"), Option::Some("Accept"));
+
+        let response = client
+            .get(uri.clone())
+            .header(Header::new("Accept", "text/html, application/json;q=0"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotAcceptable);
+
+        let response = client
+            .get(uri)
+            .header(Header::new("Accept", "text/html, application/*;q=0.5"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Option::Some(ContentType::JSON));
+    }
+
+    #[test]
+    fn import_too_large_feed_test() {
+        let client: Client = Client::new(super::super::rocket()).unwrap();
+
+        let response = client
+            .post("/feeds/import")
+            .body(vec![b' '; super::super::DOCUMENT_LIMIT as usize + 1])
+            .dispatch();
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+    }
+
+    #[test]
+    fn prune_feed_test() {
+        let client: Client = Client::new(super::super::rocket()).unwrap();
+        let feed: Feed = create_feed(&client, "Title", "news");
+        let uri: String = format!("/feeds/{}/prune", feed.get_uuid().unwrap());
+
+        // Only administrators prune feeds
+        let response = client.post(uri.clone()).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client
+            .post(uri)
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn list_feeds_test() {
+        let client: Client = Client::new(super::super::rocket()).unwrap();
+        create_feed(&client, "B", "news");
+        create_feed(&client, "A", "news");
+        create_feed(&client, "C", "sports");
+        create_feed(&client, "D", "news");
+
+        let mut response = client
+            .get("/feeds?sort=title&limit=2&category=news")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let page: Page<Feed> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let titles: Vec<String> = page
+            .items
+            .into_iter()
+            .filter_map(|feed| feed.title)
+            .collect();
+        assert_eq!(titles, vec!["A", "B"]);
+        assert_eq!(page.next, Option::Some("2".to_string()));
+
+        let mut response = client
+            .get("/feeds?sort=title&limit=2&category=news&cursor=2")
+            .dispatch();
+        let page: Page<Feed> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.next, Option::None);
+    }
+}
@@ -1,33 +1,93 @@
+mod auth;
 mod catchers;
+mod conditional;
 mod feed_items;
 mod feeds;
+mod opml;
+mod patch;
 
-use crate::common::errors::{Error, UuidError};
+use crate::common::errors::{Error, SyndicationError, UuidError};
 
-use std::str::FromStr;
+use std::{io::Read, str::FromStr};
 
+use rocket::{fairing::AdHoc, Data, Rocket};
 use uuid::Uuid;
 
 const SCOPE: &str = "router";
 
+/// The largest document that can be imported, in bytes
+const DOCUMENT_LIMIT: u64 = 2 * 1024 * 1024;
+
+/// The url feeder is reachable at
+pub struct PublicUrl(pub String);
+
+/// Whether modifications must be conditioned on the current checksum of what they modify
+pub struct RequireIfMatch(pub bool);
+
+/// The bearer token that authorizes administrative requests, if there is one
+pub struct AdminToken(pub Option<String>);
+
 /// Start the router
 pub fn start() {
+    rocket().launch();
+}
+
+/// Build the router, along with its fairings, routes and catchers
+pub fn rocket() -> Rocket {
     rocket::ignite()
-        .attach(crate::db::DbConnection::fairing())
+        .attach(crate::db::Store::fairing())
+        .attach(crate::db::migrations_fairing())
+        .attach(crate::db::retention::sweeper_fairing())
+        .attach(AdHoc::on_attach("Public url", |rocket| {
+            let public_url: String = rocket
+                .config()
+                .get_string("public_url")
+                .unwrap_or_else(|_| String::from("http://localhost:8000"));
+            Ok(rocket.manage(PublicUrl(public_url)))
+        }))
+        .attach(AdHoc::on_attach("Require if match", |rocket| {
+            let require_if_match: bool = rocket
+                .config()
+                .get_bool("require_if_match")
+                .unwrap_or(false);
+            Ok(rocket.manage(RequireIfMatch(require_if_match)))
+        }))
+        .attach(AdHoc::on_attach("Admin token", |rocket| {
+            let admin_token: Option<String> = rocket
+                .config()
+                .get_string("admin_token")
+                .ok()
+                .filter(|value| !value.is_empty());
+            Ok(rocket.manage(AdminToken(admin_token)))
+        }))
         .mount(
             "/",
             routes![
+                feeds::list_feeds,
                 feeds::get_feed,
                 feeds::get_feed_checksum,
+                feeds::get_feed_rss,
+                feeds::get_feed_atom,
+                feeds::get_feed_json_feed,
                 feeds::create_feed,
+                feeds::import_feed,
                 feeds::update_feed,
+                feeds::merge_patch_feed,
+                feeds::json_patch_feed,
+                feeds::prune_feed,
                 feeds::delete_feed,
                 feed_items::get_all_feed_items,
                 feed_items::get_specific_feed_items,
                 feed_items::get_feed_item_checksum,
                 feed_items::create_feed_item,
                 feed_items::update_feed_item,
+                feed_items::merge_patch_feed_item,
+                feed_items::json_patch_feed_item,
+                feed_items::publish_feed_item,
+                feed_items::archive_feed_item,
                 feed_items::delete_feed_item,
+                opml::get_opml,
+                opml::import_opml,
             ],
         )
         .register(catchers![
@@ -41,7 +101,6 @@ pub fn start() {
             catchers::http_501_not_implemented,
             catchers::http_503_service_unavailable,
         ])
-        .launch();
 }
 
 /// Check an Uuid
@@ -56,3 +115,43 @@ fn check_uuid(uuid: String, scope: &str) -> Result<Uuid, Error> {
         )),
     }
 }
+
+/// Read an imported document, up to the document size limit
+fn read_document(data: Data, scope: &str) -> Result<String, Error> {
+    let mut document: String = String::new();
+    // One more byte than the limit is read to tell a document at the limit from a larger one
+    if let Err(e) = data
+        .open()
+        .take(DOCUMENT_LIMIT + 1)
+        .read_to_string(&mut document)
+    {
+        return Result::Err(create_error!(
+            scope,
+            SyndicationError::InvalidDocument {
+                err: format!("{}", e)
+            }
+        ));
+    }
+
+    if document.len() as u64 > DOCUMENT_LIMIT {
+        return Result::Err(create_error!(
+            scope,
+            SyndicationError::DocumentTooLarge {
+                limit: DOCUMENT_LIMIT
+            }
+        ));
+    }
+    Result::Ok(document)
+}
+
+/// The bearer token of the administrators in the tests of the router
+#[cfg(all(test, feature = "inmemory"))]
+const TEST_ADMIN_TOKEN: &str = "admin";
+
+/// Build the router for a test, with TEST_ADMIN_TOKEN as its admin token
+#[cfg(all(test, feature = "inmemory"))]
+fn test_rocket() -> Rocket {
+    // The environment overrides the admin token of Rocket.toml
+    std::env::set_var("ROCKET_ADMIN_TOKEN", TEST_ADMIN_TOKEN);
+    rocket()
+}
@@ -0,0 +1,82 @@
+use super::{read_document, PublicUrl};
+
+use crate::{
+    common::{report::Report, DocumentResult, JsonResult},
+    db::{model::Feed, FeedWrapper, Store},
+    json_result,
+    syndication::opml::{self, OpmlImportSummary},
+};
+
+use log::*;
+use rocket::{response::Content, Data, State};
+
+const SCOPE: &str = "router/opml";
+
+#[get("/opml")]
+pub fn get_opml(store: Store, public_url: State<PublicUrl>) -> DocumentResult {
+    match store.clone().get_feeds() {
+        Ok(value) => Result::Ok(Content(
+            opml::content_type(),
+            opml::render(&value, &public_url.0),
+        )),
+        Err(e) => Result::Err(e),
+    }
+}
+
+#[post("/opml", data = "<data>")]
+pub fn import_opml(store: Store, data: Data) -> JsonResult<Report<OpmlImportSummary>> {
+    let document: String;
+    match read_document(data, SCOPE) {
+        Ok(value) => document = value,
+        Err(e) => {
+            warn!("failed to receive the opml document");
+            json_result!(Result::Err(e))
+        }
+    }
+
+    let models: Vec<Result<Feed, String>>;
+    match opml::parse(&document) {
+        Ok(value) => models = value,
+        Err(e) => {
+            warn!("failed to read the opml document");
+            json_result!(Result::Err(e))
+        }
+    }
+
+    let mut feeds: Vec<Feed> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for model in models {
+        let feed: Feed = match model {
+            Ok(value) => value,
+            Err(e) => {
+                info!("skipping opml outline: {}", e);
+                errors.push(e);
+                continue;
+            }
+        };
+
+        match store.clone().create_feed(feed) {
+            Ok(value) => feeds.push(value),
+            Err(e) => {
+                warn!("failed to create feed from opml outline: {}", e);
+                errors.push(format!("{}", e));
+            }
+        }
+    }
+
+    let summary: OpmlImportSummary = OpmlImportSummary {
+        imported: feeds.len(),
+        skipped: errors.len(),
+        feeds,
+        errors,
+    };
+    json_result!(Result::Ok(Report::new_with_data(
+        SCOPE.to_string(),
+        format!(
+            "imported {} feeds, skipped {} feeds",
+            summary.imported, summary.skipped
+        ),
+        summary
+    )))
+}
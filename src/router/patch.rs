@@ -0,0 +1,121 @@
+use crate::common::errors::{Error, PatchError};
+
+use json_patch::Patch;
+use log::*;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+const SCOPE: &str = "router/patch";
+
+/// Fields managed by feeder, they keep their stored values whatever the patch does
+const PROTECTED_FIELDS: &[&str] = &[
+    "uuid",
+    "feed_uuid",
+    "checksum",
+    "items",
+    "created_at",
+    "updated_at",
+    "state",
+];
+
+/// A patch document received by a PATCH route
+pub enum PatchDocument {
+    /// JSON Merge Patch(RFC 7396)
+    Merge(Value),
+    /// JSON Patch(RFC 6902)
+    Json(Patch),
+}
+
+/// Apply a patch document on top of a stored model
+pub fn apply<T>(model: &T, document: &PatchDocument) -> Result<T, Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let stored: Value;
+    match serde_json::to_value(model) {
+        Ok(value) => stored = value,
+        Err(e) => {
+            warn!("failed to serialize the model: {:?}", e);
+            return Result::Err(create_error!(SCOPE, PatchError::FailedToSerialize));
+        }
+    }
+
+    let mut patched: Value = stored.clone();
+    match document {
+        PatchDocument::Merge(patch) => json_patch::merge(&mut patched, patch),
+        PatchDocument::Json(patch) => {
+            if let Err(e) = json_patch::patch(&mut patched, patch) {
+                warn!("failed to apply the patch: {}", e);
+                return Result::Err(match e {
+                    json_patch::PatchError::InvalidPointer => {
+                        create_error!(SCOPE, PatchError::InvalidPointer)
+                    }
+                    json_patch::PatchError::TestFailed => {
+                        create_error!(SCOPE, PatchError::TestFailed)
+                    }
+                });
+            }
+        }
+    }
+
+    if let (Value::Object(patched_fields), Value::Object(stored_fields)) = (&mut patched, &stored) {
+        for field in PROTECTED_FIELDS {
+            match stored_fields.get(*field) {
+                Some(value) => patched_fields.insert(field.to_string(), value.clone()),
+                None => patched_fields.remove(*field),
+            };
+        }
+    }
+
+    match serde_json::from_value(patched) {
+        Ok(value) => Result::Ok(value),
+        Err(e) => {
+            warn!("the patched model is not valid: {}", e);
+            Result::Err(create_error!(
+                SCOPE,
+                PatchError::InvalidModel {
+                    err: format!("{}", e)
+                }
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply, PatchDocument};
+
+    use crate::db::model::Feed;
+
+    #[test]
+    fn merge_patch_test() {
+        let feed = Feed::new("Title", "Description", "https://example.com").unwrap();
+        let patch = serde_json::json!({
+            "title": "New title",
+            "category": "news",
+            "uuid": "00000000-0000-0000-0000-000000000000"
+        });
+
+        let patched: Feed = apply(&feed, &PatchDocument::Merge(patch)).unwrap();
+
+        assert_eq!(patched.title, Option::Some("New title".to_string()));
+        assert_eq!(patched.category, Option::Some("news".to_string()));
+        assert_eq!(patched.description, feed.description);
+        assert_eq!(patched.get_uuid(), feed.get_uuid());
+    }
+
+    #[test]
+    fn json_patch_test() {
+        let feed = Feed::new("Title", "Description", "https://example.com").unwrap();
+        let patch = serde_json::from_value(serde_json::json!([
+            {"op": "test", "path": "/title", "value": "Title"},
+            {"op": "remove", "path": "/description"}
+        ]))
+        .unwrap();
+
+        let patched: Feed = apply(&feed, &PatchDocument::Json(patch)).unwrap();
+
+        assert!(patched.description.is_none());
+        assert!(patched.validate().is_some());
+    }
+}
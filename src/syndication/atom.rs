@@ -0,0 +1,155 @@
+use super::{escape, write_element};
+
+use crate::db::model::{Feed, FeedItem, ItemsVec};
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+
+/// Render a feed as an Atom 1.0 document
+/// Only the items of a feed with full items are rendered
+pub fn render(feed: &Feed) -> String {
+    let mut xml = String::new();
+    // Feeds stored before they had an update time use the latest time of their items, so that the
+    // document only changes when what it renders changes
+    let updated: DateTime<Utc> = feed
+        .get_updated_at()
+        .unwrap_or_else(|| latest_item_time(feed));
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    match &feed.language {
+        Some(language) => xml.push_str(&format!(
+            "<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"{}\">\n",
+            escape(language)
+        )),
+        None => xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n"),
+    }
+
+    // id, title and updated are required by the specification
+    let title: String = feed.title.clone().unwrap_or_default();
+    if let Some(uuid) = feed.get_uuid() {
+        xml.push_str(&format!("  <id>urn:uuid:{}</id>\n", uuid));
+    }
+    write_element(&mut xml, 2, "title", Some(&title));
+    write_element(&mut xml, 2, "subtitle", feed.description.as_ref());
+    write_element(&mut xml, 2, "updated", Some(&atom_date(&updated)));
+    if let Some(link) = &feed.link {
+        xml.push_str(&format!("  <link href=\"{}\"/>\n", escape(link)));
+    }
+
+    // feeds have no author of their own, so the feed title is used
+    // in order to cover the entries that do not have an author either
+    xml.push_str("  <author>\n");
+    write_element(&mut xml, 4, "name", Some(&title));
+    xml.push_str("  </author>\n");
+
+    write_element(&mut xml, 2, "rights", feed.copyright.as_ref());
+    if let Some(category) = &feed.category {
+        xml.push_str(&format!("  <category term=\"{}\"/>\n", escape(category)));
+    }
+    if let Some(image) = &feed.image {
+        write_element(&mut xml, 2, "logo", Some(&image.url));
+    }
+
+    if let Some(ItemsVec::Full(items)) = &feed.items {
+        for item in items {
+            render_entry(&mut xml, item, &updated);
+        }
+    }
+
+    xml.push_str("</feed>\n");
+
+    xml
+}
+
+/// Get the latest update or publication time of the items of a feed
+/// Feeds without any timed item are dated at the unix epoch
+fn latest_item_time(feed: &Feed) -> DateTime<Utc> {
+    let mut latest: DateTime<Utc> = Utc.timestamp(0, 0);
+    if let Some(ItemsVec::Full(items)) = &feed.items {
+        for item in items {
+            if let Some(time) = item.get_updated_at().or(item.published_at) {
+                latest = latest.max(time);
+            }
+        }
+    }
+    latest
+}
+
+/// Format a time as an Atom date
+fn atom_date(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Render a feed item as an Atom 1.0 entry
+/// Items without an update time of their own use the update time of the feed
+fn render_entry(xml: &mut String, item: &FeedItem, feed_updated: &DateTime<Utc>) {
+    xml.push_str("  <entry>\n");
+
+    if let Some(uuid) = item.get_uuid() {
+        xml.push_str(&format!("    <id>urn:uuid:{}</id>\n", uuid));
+    }
+    let title: String = item.title.clone().unwrap_or_default();
+    write_element(xml, 4, "title", Some(&title));
+    let updated: DateTime<Utc> = item
+        .get_updated_at()
+        .or(item.published_at)
+        .unwrap_or(*feed_updated);
+    write_element(xml, 4, "updated", Some(&atom_date(&updated)));
+    let published: Option<String> = item.published_at.map(|time| atom_date(&time));
+    write_element(xml, 4, "published", published.as_ref());
+    if let Some(link) = &item.link {
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", escape(link)));
+    }
+    write_element(xml, 4, "summary", item.description.as_ref());
+
+    if let Some(author) = &item.author {
+        xml.push_str("    <author>\n");
+        write_element(xml, 6, "name", Some(author));
+        xml.push_str("    </author>\n");
+    }
+    if let Some(comments) = &item.comments {
+        xml.push_str(&format!(
+            "    <link rel=\"replies\" href=\"{}\"/>\n",
+            escape(comments)
+        ));
+    }
+    if let Some(enclosure) = &item.enclosure {
+        xml.push_str(&format!(
+            "    <link rel=\"enclosure\" href=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+            escape(&enclosure.url),
+            escape(&enclosure.length),
+            escape(&enclosure._type)
+        ));
+    }
+
+    xml.push_str("  </entry>\n");
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+
+    use crate::db::model::{Feed, FeedItem, ItemsVec};
+
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn render_test() {
+        let mut feed = Feed::new("Title & co", "A description", "https://example.com").unwrap();
+        let mut item = FeedItem::new("Item", "https://example.com/item", "<b>bold</b>").unwrap();
+        item.published_at = Option::Some(Utc.ymd(2020, 1, 2).and_hms(3, 4, 5));
+        feed.items = Option::Some(ItemsVec::Full(vec![item.clone()]));
+
+        let xml = render(&feed);
+
+        assert!(xml.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert!(xml.contains("<title>Title &amp; co</title>"));
+        assert!(xml.contains("<summary>&lt;b&gt;bold&lt;/b&gt;</summary>"));
+        assert!(xml.contains("<published>2020-01-02T03:04:05Z</published>"));
+        assert!(xml.contains(&format!("<id>urn:uuid:{}</id>", item.get_uuid().unwrap())));
+        // Without an update time of its own, the feed is dated by its latest item
+        assert!(xml.contains("  <updated>2020-01-02T03:04:05Z</updated>"));
+
+        // The document is the same as long as the feed is
+        assert_eq!(xml, render(&feed));
+    }
+}
@@ -0,0 +1,347 @@
+use super::tree::{self, Element};
+
+use crate::{
+    common::errors::{Error, ModelError, SyndicationError},
+    db::model::{aux, Feed, FeedItem},
+};
+
+use chrono::{DateTime, Utc};
+use log::*;
+
+const SCOPE: &str = "syndication/import";
+
+/// The namespace of the atom 1.0 elements
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+
+/// A feed read from a rss or atom document
+pub struct ImportedFeed {
+    pub feed: Feed,
+    /// The items of the feed in the order of the document, or the reason they could not be read
+    pub items: Vec<Result<FeedItem, String>>,
+}
+
+/// Summary of a feed import
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportSummary {
+    pub feed: Feed,
+    pub imported: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+/// Read a rss 2.0 or atom 1.0 document into a feed and its items
+pub fn parse(document: &str) -> Result<ImportedFeed, Error> {
+    let root: Element;
+    match tree::parse(document) {
+        Ok(value) => root = value,
+        Err(e) => {
+            warn!("failed to parse the document: {}", e);
+            return Result::Err(create_error!(
+                SCOPE,
+                SyndicationError::InvalidDocument { err: e }
+            ));
+        }
+    }
+
+    // Rss elements are in no namespace, atom elements are in the atom namespace
+    match (
+        root.name.as_str(),
+        root.namespace.as_ref().map(String::as_str),
+    ) {
+        ("rss", None) => parse_rss(&root),
+        ("feed", Some(ATOM_NAMESPACE)) => parse_atom(&root),
+        _ => {
+            warn!("unsupported document with the root element {}", root.name);
+            Result::Err(create_error!(SCOPE, SyndicationError::UnsupportedDocument))
+        }
+    }
+}
+
+/// Read a rss 2.0 document
+fn parse_rss(root: &Element) -> Result<ImportedFeed, Error> {
+    let channel: &Element;
+    match root.child("channel") {
+        Some(value) => channel = value,
+        None => return Result::Err(create_error!(SCOPE, SyndicationError::NoChannel)),
+    }
+
+    let mut feed: Feed = new_feed(
+        channel.child_text("title"),
+        channel.child_text("description"),
+        channel.child_text("link"),
+    )?;
+    feed.category = channel.child_text("category");
+    feed.copyright = channel.child_text("copyright");
+    feed.language = channel.child_text("language");
+    if let Some(image) = channel.child("image") {
+        if let Some(url) = image.child_text("url") {
+            feed.image = Option::Some(aux::FeedImage {
+                url,
+                title: image.child_text("title").unwrap_or_default(),
+                link: image.child_text("link").unwrap_or_default(),
+            });
+        }
+    }
+
+    let items: Vec<Result<FeedItem, String>> = channel
+        .children("item")
+        .enumerate()
+        .map(|(index, item)| parse_rss_item(item).map_err(|e| format!("item {}: {}", index, e)))
+        .collect();
+
+    Result::Ok(ImportedFeed { feed, items })
+}
+
+/// Read a rss 2.0 item
+fn parse_rss_item(element: &Element) -> Result<FeedItem, String> {
+    let mut item: FeedItem = new_item(
+        element.child_text("title"),
+        element.child_text("link"),
+        element.child_text("description"),
+    )?;
+    item.author = element.child_text("author");
+    item.comments = element.child_text("comments");
+    item.published_at = element
+        .child_text("pubDate")
+        .and_then(|value| DateTime::parse_from_rfc2822(&value).ok())
+        .map(|value| value.with_timezone(&Utc));
+    if let Some(enclosure) = element.child("enclosure") {
+        if let Some(url) = enclosure.attribute("url") {
+            item.enclosure = Option::Some(aux::FeedItemEnclosure {
+                url,
+                length: enclosure.attribute("length").unwrap_or_default(),
+                _type: enclosure.attribute("type").unwrap_or_default(),
+            });
+        }
+    }
+
+    Result::Ok(item)
+}
+
+/// Read an atom 1.0 document
+fn parse_atom(root: &Element) -> Result<ImportedFeed, Error> {
+    let mut feed: Feed = new_feed(
+        root.child_text("title"),
+        root.child_text("subtitle"),
+        atom_link(root, "alternate").and_then(|link| link.attribute("href")),
+    )?;
+    feed.category = root
+        .child("category")
+        .and_then(|category| category.attribute("term"));
+    feed.copyright = root.child_text("rights");
+    feed.language = root.attribute("lang");
+    if let Some(url) = root.child_text("logo").or_else(|| root.child_text("icon")) {
+        feed.image = Option::Some(aux::FeedImage {
+            url,
+            title: feed.title.clone().unwrap_or_default(),
+            link: feed.link.clone().unwrap_or_default(),
+        });
+    }
+
+    let items: Vec<Result<FeedItem, String>> = root
+        .children("entry")
+        .enumerate()
+        .map(|(index, entry)| {
+            parse_atom_entry(entry).map_err(|e| format!("entry {}: {}", index, e))
+        })
+        .collect();
+
+    Result::Ok(ImportedFeed { feed, items })
+}
+
+/// Read an atom 1.0 entry
+fn parse_atom_entry(element: &Element) -> Result<FeedItem, String> {
+    let mut item: FeedItem = new_item(
+        element.child_text("title"),
+        atom_link(element, "alternate").and_then(|link| link.attribute("href")),
+        element
+            .child_text("summary")
+            .or_else(|| element.child_text("content")),
+    )?;
+    item.author = element
+        .child("author")
+        .and_then(|author| author.child_text("name"));
+    item.comments = atom_link(element, "replies").and_then(|link| link.attribute("href"));
+    item.published_at = element
+        .child_text("published")
+        .or_else(|| element.child_text("updated"))
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|value| value.with_timezone(&Utc));
+    if let Some(enclosure) = atom_link(element, "enclosure") {
+        if let Some(url) = enclosure.attribute("href") {
+            item.enclosure = Option::Some(aux::FeedItemEnclosure {
+                url,
+                length: enclosure.attribute("length").unwrap_or_default(),
+                _type: enclosure.attribute("type").unwrap_or_default(),
+            });
+        }
+    }
+
+    Result::Ok(item)
+}
+
+/// Get the first atom link with the given relation
+/// Links without a relation are alternate links
+fn atom_link<'a>(element: &'a Element, rel: &str) -> Option<&'a Element> {
+    element
+        .children("link")
+        .find(|link| match link.attribute("rel") {
+            Some(value) => value == rel,
+            None => rel == "alternate",
+        })
+}
+
+/// Create a feed from the required fields of a document
+/// The title is used as the description if the document has no description
+fn new_feed(
+    title: Option<String>,
+    description: Option<String>,
+    link: Option<String>,
+) -> Result<Feed, Error> {
+    let good_title: String;
+    match title {
+        Some(value) => good_title = value,
+        None => return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoTitle)),
+    }
+    let good_link: String;
+    match link {
+        Some(value) => good_link = value,
+        None => return Result::Err(create_error!(SCOPE, ModelError::ModelHasNoLink)),
+    }
+    let good_description: String = description.unwrap_or_else(|| good_title.clone());
+
+    Feed::new(&good_title, &good_description, &good_link)
+}
+
+/// Create a feed item from the required fields of a document
+/// Items need a title or a description(as per the rss specification), the missing one is filled
+/// in with the other
+fn new_item(
+    title: Option<String>,
+    link: Option<String>,
+    description: Option<String>,
+) -> Result<FeedItem, String> {
+    let good_link: String;
+    match link {
+        Some(value) => good_link = value,
+        None => return Result::Err(format!("{}", ModelError::ModelHasNoLink)),
+    }
+
+    let (good_title, good_description): (String, String) = match (title, description) {
+        (Some(title), Some(description)) => (title, description),
+        (Some(title), None) => (title.clone(), title),
+        (None, Some(description)) => (description.clone(), description),
+        (None, None) => return Result::Err(format!("{}", ModelError::ModelHasNoTitle)),
+    };
+
+    FeedItem::new(&good_title, &good_link, &good_description).map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn parse_rss_test() {
+        let document = r#"<?xml version="1.0"?>
+            <rss version="2.0">
+              <channel>
+                <title>Example</title>
+                <link>https://example.com</link>
+                <description>An example feed</description>
+                <item>
+                  <title>First</title>
+                  <link>https://example.com/1</link>
+                  <description><![CDATA[<p>first item</p>]]></description>
+                  <enclosure url="https://example.com/1.mp3" length="42" type="audio/mpeg"/>
+                </item>
+                <item>
+                  <title>No link</title>
+                </item>
+              </channel>
+            </rss>"#;
+
+        let imported = parse(document).unwrap();
+
+        assert_eq!(imported.feed.title, Option::Some("Example".to_string()));
+        assert_eq!(imported.items.len(), 2);
+
+        let item = imported.items[0].clone().unwrap();
+        assert_eq!(
+            item.description,
+            Option::Some("<p>first item</p>".to_string())
+        );
+        assert_eq!(item.enclosure.unwrap().length, "42");
+        assert!(imported.items[1].is_err());
+    }
+
+    #[test]
+    fn parse_atom_test() {
+        let document = r#"<?xml version="1.0"?>
+            <feed xmlns="http://www.w3.org/2005/Atom" xml:lang="en">
+              <title>Example</title>
+              <link href="https://example.com"/>
+              <link rel="self" href="https://example.com/atom"/>
+              <entry>
+                <title>First</title>
+                <link href="https://example.com/1"/>
+                <summary>first entry</summary>
+                <author><name>Someone</name></author>
+              </entry>
+            </feed>"#;
+
+        let imported = parse(document).unwrap();
+
+        assert_eq!(
+            imported.feed.link,
+            Option::Some("https://example.com".to_string())
+        );
+        assert_eq!(imported.feed.language, Option::Some("en".to_string()));
+
+        let item = imported.items[0].clone().unwrap();
+        assert_eq!(item.author, Option::Some("Someone".to_string()));
+    }
+
+    #[test]
+    fn parse_rss_extensions_test() {
+        // As published by wordpress, with extensions before the rss elements they resemble
+        let document = r#"<?xml version="1.0"?>
+            <rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"
+              xmlns:media="http://search.yahoo.com/mrss/">
+              <channel>
+                <atom:link href="https://example.com/feed/" rel="self" type="application/rss+xml"/>
+                <title>Example</title>
+                <link>https://example.com</link>
+                <description>An example feed</description>
+                <item>
+                  <media:title>Media title</media:title>
+                  <title>First</title>
+                  <link>https://example.com/1</link>
+                </item>
+              </channel>
+            </rss>"#;
+
+        let imported = parse(document).unwrap();
+
+        assert_eq!(
+            imported.feed.link,
+            Option::Some("https://example.com".to_string())
+        );
+        let item = imported.items[0].clone().unwrap();
+        assert_eq!(item.title, Option::Some("First".to_string()));
+    }
+
+    #[test]
+    fn parse_unknown_namespace_test() {
+        // Atom 0.3 documents have the same root element as atom 1.0 ones
+        let document = r#"<?xml version="1.0"?>
+            <feed xmlns="http://purl.org/atom/ns#">
+              <title>Example</title>
+            </feed>"#;
+
+        assert_eq!(
+            parse(document).err().unwrap().code(),
+            "document_unsupported"
+        );
+    }
+}
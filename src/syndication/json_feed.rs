@@ -0,0 +1,181 @@
+use crate::{
+    common::time,
+    db::model::{Feed, FeedItem, ItemsVec},
+};
+
+use serde::Serialize;
+
+const VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// JSON Feed 1.1 representation of a feed
+#[derive(Clone, Debug, Serialize)]
+pub struct JsonFeed {
+    version: &'static str,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    home_page_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    items: Vec<JsonFeedItem>,
+}
+
+/// JSON Feed 1.1 representation of a feed item
+#[derive(Clone, Debug, Serialize)]
+pub struct JsonFeedItem {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    content_html: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_modified: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonFeedAuthor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<JsonFeedAttachment>,
+}
+
+/// JSON Feed 1.1 author
+#[derive(Clone, Debug, Serialize)]
+pub struct JsonFeedAuthor {
+    name: String,
+}
+
+/// JSON Feed 1.1 attachment, the equivalent of a RSS enclosure
+#[derive(Clone, Debug, Serialize)]
+pub struct JsonFeedAttachment {
+    url: String,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_in_bytes: Option<u64>,
+}
+
+impl From<&Feed> for JsonFeed {
+    fn from(feed: &Feed) -> Self {
+        let mut items: Vec<JsonFeedItem> = Vec::new();
+        if let Some(ItemsVec::Full(feed_items)) = &feed.items {
+            for item in feed_items {
+                items.push(JsonFeedItem::from(item));
+            }
+        }
+
+        JsonFeed {
+            version: VERSION,
+            title: feed.title.clone().unwrap_or_default(),
+            home_page_url: feed.link.clone(),
+            description: feed.description.clone(),
+            icon: feed.image.as_ref().map(|image| image.url.clone()),
+            language: feed.language.clone(),
+            items,
+        }
+    }
+}
+
+impl From<&FeedItem> for JsonFeedItem {
+    fn from(item: &FeedItem) -> Self {
+        let mut authors: Vec<JsonFeedAuthor> = Vec::new();
+        if let Some(author) = &item.author {
+            authors.push(JsonFeedAuthor {
+                name: author.clone(),
+            });
+        }
+
+        let mut attachments: Vec<JsonFeedAttachment> = Vec::new();
+        if let Some(enclosure) = &item.enclosure {
+            attachments.push(JsonFeedAttachment {
+                url: enclosure.url.clone(),
+                mime_type: enclosure._type.clone(),
+                size_in_bytes: enclosure.length.parse::<u64>().ok(),
+            });
+        }
+
+        JsonFeedItem {
+            id: item
+                .get_uuid()
+                .map(|uuid| format!("{}", uuid))
+                .unwrap_or_default(),
+            url: item.link.clone(),
+            title: item.title.clone(),
+            content_html: item.description.clone().unwrap_or_default(),
+            date_published: item.published_at.map(|value| time::to_rfc3339(&value)),
+            date_modified: item.get_updated_at().map(|value| time::to_rfc3339(&value)),
+            authors,
+            attachments,
+        }
+    }
+}
+
+/// Render a feed as a JSON Feed 1.1 document
+/// Only the items of a feed with full items are rendered
+pub fn render(feed: &Feed) -> String {
+    // serializing these structs cannot fail: they only contain strings and vectors
+    serde_json::to_string_pretty(&JsonFeed::from(feed)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+
+    use crate::db::model::{aux::FeedItemEnclosure, Feed, FeedItem, ItemsVec};
+
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn render_test() {
+        let mut feed = Feed::new("Title", "A description", "https://example.com").unwrap();
+        feed.language = Option::Some("en".to_string());
+        let mut item = FeedItem::new("Item", "https://example.com/item", "<b>bold</b>").unwrap();
+        item.author = Option::Some("Author".to_string());
+        item.published_at = Option::Some(Utc.ymd(2020, 1, 2).and_hms(3, 4, 5));
+        item.enclosure = Option::Some(FeedItemEnclosure {
+            url: "https://example.com/item.mp3".to_string(),
+            length: "1024".to_string(),
+            _type: "audio/mpeg".to_string(),
+        });
+        feed.items = Option::Some(ItemsVec::Full(vec![item.clone()]));
+
+        let document: Value = serde_json::from_str(&render(&feed)).unwrap();
+
+        assert_eq!(
+            document,
+            json!({
+                "version": "https://jsonfeed.org/version/1.1",
+                "title": "Title",
+                "home_page_url": "https://example.com",
+                "description": "A description",
+                "language": "en",
+                "items": [{
+                    "id": format!("{}", item.get_uuid().unwrap()),
+                    "url": "https://example.com/item",
+                    "title": "Item",
+                    "content_html": "<b>bold</b>",
+                    "date_published": "2020-01-02T03:04:05.000Z",
+                    "authors": [{"name": "Author"}],
+                    "attachments": [{
+                        "url": "https://example.com/item.mp3",
+                        "mime_type": "audio/mpeg",
+                        "size_in_bytes": 1024
+                    }]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn render_without_items_test() {
+        let feed = Feed::new("Title", "A description", "https://example.com").unwrap();
+
+        let document: Value = serde_json::from_str(&render(&feed)).unwrap();
+
+        assert_eq!(document["items"], json!([]));
+        assert!(document.get("icon").is_none());
+    }
+}
@@ -0,0 +1,97 @@
+pub mod atom;
+pub mod import;
+pub mod json_feed;
+pub mod opml;
+pub mod rss;
+pub mod tree;
+
+use crate::db::model::Feed;
+
+use rocket::http::{ContentType, MediaType};
+
+/// Syndication formats that feeder can render feeds in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Rss,
+    Atom,
+    JsonFeed,
+}
+
+impl Format {
+    /// Get the format that corresponds to a media type, if there is any
+    pub fn from_media_type(media_type: &MediaType) -> Option<Self> {
+        for format in &[Format::Rss, Format::Atom, Format::JsonFeed] {
+            if format.content_type().media_type() == media_type {
+                return Option::Some(*format);
+            }
+        }
+        Option::None
+    }
+
+    /// Get the content type of a document in this format
+    pub fn content_type(self) -> ContentType {
+        match self {
+            Format::Rss => ContentType::new("application", "rss+xml"),
+            Format::Atom => ContentType::new("application", "atom+xml"),
+            Format::JsonFeed => ContentType::new("application", "feed+json"),
+        }
+    }
+
+    /// Get the name of this format, used to tell its representations apart
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Rss => "rss",
+            Format::Atom => "atom",
+            Format::JsonFeed => "json-feed",
+        }
+    }
+
+    /// Render a feed in this format
+    pub fn render(self, feed: &Feed) -> String {
+        match self {
+            Format::Rss => rss::render(feed),
+            Format::Atom => atom::render(feed),
+            Format::JsonFeed => json_feed::render(feed),
+        }
+    }
+}
+
+/// Escape a string so that it can be used as xml text or as an attribute value
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+/// Write a simple xml element containing text
+/// Nothing is written if there is no value
+fn write_element(xml: &mut String, indent: usize, name: &str, value: Option<&String>) {
+    if let Some(text) = value {
+        xml.push_str(&" ".repeat(indent));
+        xml.push_str(&format!("<{}>{}</{}>\n", name, escape(text), name));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::escape;
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use super::{
+    escape,
+    tree::{self, Element},
+};
+
+use crate::{
+    common::errors::{Error, ModelError, SyndicationError},
+    db::model::Feed,
+};
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use log::*;
+use rocket::http::ContentType;
+
+const SCOPE: &str = "syndication/opml";
+
+/// Summary of an opml import
+#[derive(Clone, Debug, Serialize)]
+pub struct OpmlImportSummary {
+    pub feeds: Vec<Feed>,
+    pub imported: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+/// Get the content type of an opml document
+pub fn content_type() -> ContentType {
+    ContentType::new("text", "x-opml")
+}
+
+/// Render an OPML 2.0 outline of the given feeds
+/// Feeds are grouped by their category and link to their rss rendering served at public_url
+pub fn render(feeds: &[Feed], public_url: &str) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<opml version=\"2.0\">\n");
+    xml.push_str("  <head>\n");
+    xml.push_str("    <title>feeder</title>\n");
+    xml.push_str(&format!(
+        "    <dateCreated>{}</dateCreated>\n",
+        Utc::now().to_rfc2822()
+    ));
+    xml.push_str("  </head>\n");
+    xml.push_str("  <body>\n");
+
+    let mut categories: BTreeMap<&str, Vec<&Feed>> = BTreeMap::new();
+    let mut uncategorized: Vec<&Feed> = Vec::new();
+    for feed in feeds {
+        match &feed.category {
+            Some(category) => categories
+                .entry(category.as_str())
+                .or_insert_with(Vec::new)
+                .push(feed),
+            None => uncategorized.push(feed),
+        }
+    }
+
+    for (category, category_feeds) in categories {
+        xml.push_str(&format!(
+            "    <outline text=\"{0}\" title=\"{0}\">\n",
+            escape(category)
+        ));
+        for feed in category_feeds {
+            render_outline(&mut xml, 6, feed, public_url);
+        }
+        xml.push_str("    </outline>\n");
+    }
+    for feed in uncategorized {
+        render_outline(&mut xml, 4, feed, public_url);
+    }
+
+    xml.push_str("  </body>\n");
+    xml.push_str("</opml>\n");
+
+    xml
+}
+
+/// Render the outline of a feed
+fn render_outline(xml: &mut String, indent: usize, feed: &Feed, public_url: &str) {
+    let title: String = escape(&feed.title.clone().unwrap_or_default());
+
+    xml.push_str(&" ".repeat(indent));
+    xml.push_str(&format!(
+        "<outline type=\"rss\" text=\"{0}\" title=\"{0}\"",
+        title
+    ));
+    if let Some(description) = &feed.description {
+        xml.push_str(&format!(" description=\"{}\"", escape(description)));
+    }
+    if let Some(uuid) = feed.get_uuid() {
+        xml.push_str(&format!(
+            " xmlUrl=\"{}\"",
+            escape(&format!(
+                "{}/feeds/{}/rss",
+                public_url.trim_end_matches('/'),
+                uuid
+            ))
+        ));
+    }
+    if let Some(link) = &feed.link {
+        xml.push_str(&format!(" htmlUrl=\"{}\"", escape(link)));
+    }
+    if let Some(language) = &feed.language {
+        xml.push_str(&format!(" language=\"{}\"", escape(language)));
+    }
+    xml.push_str(" version=\"RSS2\"/>\n");
+}
+
+/// Read the feeds of an OPML outline, or the reason they could not be read
+/// Outlines that contain other outlines and have no feed url are used as categories
+pub fn parse(document: &str) -> Result<Vec<Result<Feed, String>>, Error> {
+    let root: Element;
+    match tree::parse(document) {
+        Ok(value) => root = value,
+        Err(e) => {
+            warn!("failed to parse the opml document: {}", e);
+            return Result::Err(create_error!(
+                SCOPE,
+                SyndicationError::InvalidDocument { err: e }
+            ));
+        }
+    }
+    if root.name != "opml" {
+        warn!("unsupported document with the root element {}", root.name);
+        return Result::Err(create_error!(SCOPE, SyndicationError::UnsupportedDocument));
+    }
+
+    let body: &Element;
+    match root.child("body") {
+        Some(value) => body = value,
+        None => return Result::Err(create_error!(SCOPE, SyndicationError::NoBody)),
+    }
+
+    let mut feeds: Vec<Result<Feed, String>> = Vec::new();
+    parse_outlines(body, Option::None, &mut feeds);
+    Result::Ok(feeds)
+}
+
+/// Read the outlines of an element recursively
+fn parse_outlines(
+    element: &Element,
+    category: Option<String>,
+    feeds: &mut Vec<Result<Feed, String>>,
+) {
+    for outline in element.children("outline") {
+        let text: Option<String> = outline
+            .attribute("title")
+            .or_else(|| outline.attribute("text"));
+
+        if outline.attribute("xmlUrl").is_none() && !outline.children.is_empty() {
+            parse_outlines(outline, text, feeds);
+            continue;
+        }
+
+        let index: usize = feeds.len();
+        feeds.push(
+            parse_outline(outline, text, category.clone())
+                .map_err(|e| format!("outline {}: {}", index, e)),
+        );
+    }
+}
+
+/// Read the feed of an outline
+fn parse_outline(
+    outline: &Element,
+    title: Option<String>,
+    category: Option<String>,
+) -> Result<Feed, String> {
+    let good_title: String;
+    match title {
+        Some(value) => good_title = value,
+        None => return Result::Err(format!("{}", ModelError::ModelHasNoTitle)),
+    }
+    let good_link: String;
+    match outline
+        .attribute("htmlUrl")
+        .or_else(|| outline.attribute("xmlUrl"))
+    {
+        Some(value) => good_link = value,
+        None => return Result::Err(format!("{}", ModelError::ModelHasNoLink)),
+    }
+    let good_description: String = outline
+        .attribute("description")
+        .unwrap_or_else(|| good_title.clone());
+
+    let mut feed: Feed =
+        Feed::new(&good_title, &good_description, &good_link).map_err(|e| format!("{}", e))?;
+    feed.category = category;
+    feed.language = outline.attribute("language");
+
+    Result::Ok(feed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, render};
+
+    use crate::db::model::Feed;
+
+    #[test]
+    fn round_trip_test() {
+        let mut news = Feed::new("News & more", "Daily news", "https://example.com/news").unwrap();
+        news.category = Option::Some("news".to_string());
+        news.language = Option::Some("en".to_string());
+        let blog = Feed::new("Blog", "A blog", "https://example.com/blog").unwrap();
+
+        let document: String = render(&[news, blog], "http://localhost:8000/");
+        assert!(document.contains("title=\"News &amp; more\""));
+        assert!(document.contains("xmlUrl=\"http://localhost:8000/feeds/"));
+
+        let feeds: Vec<Feed> = parse(&document)
+            .unwrap()
+            .into_iter()
+            .map(|feed| feed.unwrap())
+            .collect();
+        assert_eq!(feeds.len(), 2);
+
+        assert_eq!(feeds[0].title, Option::Some("News & more".to_string()));
+        assert_eq!(feeds[0].description, Option::Some("Daily news".to_string()));
+        assert_eq!(
+            feeds[0].link,
+            Option::Some("https://example.com/news".to_string())
+        );
+        assert_eq!(feeds[0].category, Option::Some("news".to_string()));
+        assert_eq!(feeds[0].language, Option::Some("en".to_string()));
+
+        assert_eq!(feeds[1].title, Option::Some("Blog".to_string()));
+        assert_eq!(feeds[1].category, Option::None);
+        assert_eq!(feeds[1].language, Option::None);
+    }
+
+    #[test]
+    fn parse_invalid_outlines_test() {
+        let document: &str = "<opml version=\"2.0\"><body>\
+            <outline type=\"rss\" xmlUrl=\"https://example.com/rss\"/>\
+            <outline type=\"rss\" text=\"No link\"/>\
+            </body></opml>";
+
+        let feeds = parse(document).unwrap();
+        assert_eq!(feeds.len(), 2);
+        assert!(feeds[0].is_err());
+        assert!(feeds[1].is_err());
+
+        assert!(parse("<rss/>").is_err());
+    }
+}
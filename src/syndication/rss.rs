@@ -0,0 +1,104 @@
+use super::{escape, write_element};
+
+use crate::db::model::{Feed, FeedItem, ItemsVec};
+
+/// Render a feed as a RSS 2.0 document
+/// Only the items of a feed with full items are rendered
+pub fn render(feed: &Feed) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n");
+    xml.push_str("  <channel>\n");
+
+    // title, link and description are required by the specification
+    let title: String = feed.title.clone().unwrap_or_default();
+    let link: String = feed.link.clone().unwrap_or_default();
+    let description: String = feed.description.clone().unwrap_or_default();
+    write_element(&mut xml, 4, "title", Some(&title));
+    write_element(&mut xml, 4, "link", Some(&link));
+    write_element(&mut xml, 4, "description", Some(&description));
+    write_element(&mut xml, 4, "language", feed.language.as_ref());
+    write_element(&mut xml, 4, "copyright", feed.copyright.as_ref());
+    write_element(&mut xml, 4, "category", feed.category.as_ref());
+    let last_build_date: Option<String> = feed.get_updated_at().map(|time| time.to_rfc2822());
+    write_element(&mut xml, 4, "lastBuildDate", last_build_date.as_ref());
+
+    if let Some(image) = &feed.image {
+        xml.push_str("    <image>\n");
+        write_element(&mut xml, 6, "url", Some(&image.url));
+        write_element(&mut xml, 6, "title", Some(&image.title));
+        write_element(&mut xml, 6, "link", Some(&image.link));
+        xml.push_str("    </image>\n");
+    }
+
+    if let Some(ItemsVec::Full(items)) = &feed.items {
+        for item in items {
+            render_item(&mut xml, item);
+        }
+    }
+
+    xml.push_str("  </channel>\n");
+    xml.push_str("</rss>\n");
+
+    xml
+}
+
+/// Render a feed item as a RSS 2.0 item
+fn render_item(xml: &mut String, item: &FeedItem) {
+    xml.push_str("    <item>\n");
+
+    write_element(xml, 6, "title", item.title.as_ref());
+    write_element(xml, 6, "link", item.link.as_ref());
+    write_element(xml, 6, "description", item.description.as_ref());
+    write_element(xml, 6, "author", item.author.as_ref());
+    write_element(xml, 6, "comments", item.comments.as_ref());
+    let pub_date: Option<String> = item.published_at.map(|time| time.to_rfc2822());
+    write_element(xml, 6, "pubDate", pub_date.as_ref());
+
+    if let Some(enclosure) = &item.enclosure {
+        xml.push_str(&format!(
+            "      <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+            escape(&enclosure.url),
+            escape(&enclosure.length),
+            escape(&enclosure._type)
+        ));
+    }
+
+    if let Some(uuid) = item.get_uuid() {
+        xml.push_str(&format!(
+            "      <guid isPermaLink=\"false\">{}</guid>\n",
+            uuid
+        ));
+    }
+
+    xml.push_str("    </item>\n");
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+
+    use crate::db::model::{Feed, FeedItem, ItemsVec};
+
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn render_test() {
+        let mut feed = Feed::new("Title & co", "A description", "https://example.com").unwrap();
+        let mut item = FeedItem::new("Item", "https://example.com/item", "<b>bold</b>").unwrap();
+        item.published_at = Option::Some(Utc.ymd(2020, 1, 2).and_hms(3, 4, 5));
+        feed.items = Option::Some(ItemsVec::Full(vec![item.clone()]));
+
+        let xml = render(&feed);
+
+        assert!(xml.contains("<rss version=\"2.0\">"));
+        assert!(xml.contains("<title>Title &amp; co</title>"));
+        assert!(xml.contains("<description>&lt;b&gt;bold&lt;/b&gt;</description>"));
+        assert!(xml.contains("<pubDate>Thu, 02 Jan 2020 03:04:05 +0000</pubDate>"));
+        assert!(xml.contains(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>",
+            item.get_uuid().unwrap()
+        )));
+    }
+}
@@ -0,0 +1,95 @@
+use xml::reader::{EventReader, XmlEvent};
+
+/// A minimal xml element tree used for reading syndication documents
+#[derive(Clone, Debug, Default)]
+pub struct Element {
+    pub name: String,
+    /// The namespace uri of the element, none for elements that are not in a namespace
+    pub namespace: Option<String>,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    /// Get the first child element with the given name
+    /// Only the children in the namespace of this element are considered, so that extensions(e.g.
+    /// atom:link in a rss channel) do not shadow the elements of the document format
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children(name).next()
+    }
+
+    /// Get all the child elements with the given name, in the namespace of this element
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children
+            .iter()
+            .filter(move |child| child.name == name && child.namespace == self.namespace)
+    }
+
+    /// Get the trimmed text of the first child element with the given name
+    /// Empty text is treated as no text at all
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).and_then(|child| child.trimmed_text())
+    }
+
+    /// Get the value of an attribute of this element
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Get the trimmed text of this element
+    /// Empty text is treated as no text at all
+    pub fn trimmed_text(&self) -> Option<String> {
+        let text: &str = self.text.trim();
+        if text.is_empty() {
+            Option::None
+        } else {
+            Option::Some(text.to_string())
+        }
+    }
+}
+
+/// Parse a xml document into an element tree, returning the root element
+/// Elements keep their namespace uri, attributes are identified by their local name
+pub fn parse(document: &str) -> Result<Element, String> {
+    let mut stack: Vec<Element> = Vec::new();
+
+    for event in EventReader::from_str(document) {
+        match event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) => stack.push(Element {
+                namespace: name.namespace.filter(|value| !value.is_empty()),
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+                children: Vec::new(),
+                text: String::new(),
+            }),
+            Ok(XmlEvent::EndElement { .. }) => {
+                let element: Element = match stack.pop() {
+                    Some(value) => value,
+                    None => return Result::Err("unbalanced xml document".to_string()),
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Result::Ok(element),
+                }
+            }
+            Ok(XmlEvent::Characters(text)) | Ok(XmlEvent::CData(text)) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => return Result::Err(format!("{}", e)),
+        }
+    }
+
+    Result::Err("the xml document has no root element".to_string())
+}